@@ -1,11 +1,11 @@
 use core::fmt;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-};
+use std::cmp::Reverse;
 
 use log::{debug, info};
 use regex::{Regex, RegexSet};
+use tracing::{field::Empty, instrument, Span};
+
+use crate::input::Input;
 
 #[derive(Eq, PartialEq, Clone)]
 struct TextPosition {
@@ -43,10 +43,11 @@ fn text_to_digit(text: &str) -> &str {
 /// i.e: two -> 2
 /// # Arguments
 /// * `text` - Text to parse
-/// ```
+/// ```ignore
 /// let text = "twotwo6eightqcncghjvqfcv5";
-/// let updatedText = extract_numbers(&text);
-/// assert_eq!(updatedText, "2268qcncghjvqfcv5");
+/// let value = extract_numbers(&text);
+/// assert_eq!(value, 25);
+/// ```
 fn extract_numbers(text: &str) -> usize {
     debug!("----");
     let patterns = [
@@ -67,8 +68,7 @@ fn extract_numbers(text: &str) -> usize {
         .matches(text)
         .into_iter()
         .map(|index| &regexes[index])
-        .map(|re| re.find_iter(text))
-        .flatten()
+        .flat_map(|re| re.find_iter(text))
         .map(|m| TextPosition {
             text: text_to_digit(m.as_str()).to_string(),
             start_index: m.start(),
@@ -79,11 +79,11 @@ fn extract_numbers(text: &str) -> usize {
     debug!("{:?}", text);
     debug!("Positions: {:?}", positions);
 
-    positions.sort_by(|a, b| a.start_index.cmp(&b.start_index));
+    positions.sort_by_key(|a| a.start_index);
     let first_pos = positions.first().cloned().unwrap();
 
-    positions.sort_by(|a, b| b.end_index.cmp(&a.end_index));
-    let last_pos = positions.first().clone().unwrap();
+    positions.sort_by_key(|a| Reverse(a.end_index));
+    let last_pos = positions.first().unwrap();
 
     if first_pos.eq(last_pos) {
         return format!("{}{}", first_pos.text, first_pos.text)
@@ -91,29 +91,41 @@ fn extract_numbers(text: &str) -> usize {
             .unwrap();
     }
 
-    return format!("{}{}", first_pos.text, last_pos.text)
+    format!("{}{}", first_pos.text, last_pos.text)
         .parse()
-        .unwrap();
+        .unwrap()
 }
 
-pub fn run() {
-    info!("--- DAY 1 ----");
-
-    let filename = "./src/inputs/day_1.txt";
-    let file = File::open(filename).expect("Couldn't read file");
-    let reader = BufReader::new(file);
-
+/// Calibration value of each line, written digits included
+#[instrument(name = "parse", skip_all, fields(lines = Empty))]
+fn calibration_values(input: &Input) -> Vec<usize> {
     let mut numbers: Vec<usize> = vec![];
 
-    for line in reader.lines() {
-        if let Ok(l) = line {
-            let res = extract_numbers(&l);
-            debug!("{} -> {}", l, res);
-            numbers.push(res);
-        }
+    for l in input.lines() {
+        let res = extract_numbers(l);
+        debug!("{} -> {}", l, res);
+        numbers.push(res);
     }
 
-    let sum = numbers.into_iter().fold(0, |acc, el| acc + el);
+    Span::current().record("lines", numbers.len());
+    numbers
+}
+
+#[instrument(name = "part_2", skip_all)]
+fn resolve_part_2(numbers: &[usize]) -> usize {
+    numbers.iter().sum::<usize>()
+}
+
+/// Sum of the calibration values, written digits included (part 2)
+pub fn answers(input: &Input) -> Vec<(u8, String)> {
+    let sum = resolve_part_2(&calibration_values(input));
+    vec![(2, sum.to_string())]
+}
+
+pub fn run(input: &Input) {
+    info!("--- DAY 1 ----");
+
+    let sum = resolve_part_2(&calibration_values(input));
     info!("{}", sum);
 }
 
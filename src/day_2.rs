@@ -1,11 +1,9 @@
 use core::fmt;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-};
-
 use log::{debug, info};
 use regex::Regex;
+use tracing::{field::Empty, instrument, Span};
+
+use crate::input::Input;
 
 #[derive(Eq, PartialEq, Clone)]
 struct Game {
@@ -34,15 +32,6 @@ impl Game {
             _ => 0,
         }
     }
-
-    fn min_of(&self, color: &str) -> usize {
-        match color {
-            "red" => *self.red.iter().min().unwrap(),
-            "green" => *self.green.iter().min().unwrap(),
-            "blue" => *self.blue.iter().min().unwrap(),
-            _ => 0,
-        }
-    }
 }
 
 /// Parse the input and return the whole game info
@@ -89,9 +78,18 @@ fn read_line_input(text: &str) -> Game {
     }
     debug!("{:?}", game);
 
-    return game;
+    game
 }
 
+#[instrument(name = "parse", skip_all, fields(games = Empty))]
+fn parse_games(input: &Input) -> Vec<Game> {
+    let games: Vec<Game> = input.lines().map(read_line_input).collect();
+
+    Span::current().record("games", games.len());
+    games
+}
+
+#[instrument(name = "part_1", skip_all)]
 fn resolve_part_1(games: Vec<Game>) -> usize {
     let found_games: Vec<Game> = games
         .into_iter()
@@ -103,6 +101,7 @@ fn resolve_part_1(games: Vec<Game>) -> usize {
     found_games.into_iter().map(|game| game.id).sum::<usize>()
 }
 
+#[instrument(name = "part_2", skip_all)]
 fn resolve_part_2(games: Vec<Game>) -> usize {
     let powers: Vec<usize> = games
         .into_iter()
@@ -111,23 +110,22 @@ fn resolve_part_2(games: Vec<Game>) -> usize {
 
     debug!("powers: {:?}", powers);
 
-    return powers.iter().sum();
+    powers.iter().sum()
 }
 
-pub fn run() {
-    info!("--- DAY 2 ---");
+pub fn answers(input: &Input) -> Vec<(u8, String)> {
+    let games = parse_games(input);
 
-    let filename = "./src/inputs/day_2.txt";
-    let file = File::open(filename).expect("Couldn't open the file");
-    let reader = BufReader::new(file);
+    vec![
+        (1, resolve_part_1(games.clone()).to_string()),
+        (2, resolve_part_2(games).to_string()),
+    ]
+}
 
-    let mut games: Vec<Game> = Vec::new();
+pub fn run(input: &Input) {
+    info!("--- DAY 2 ---");
 
-    for line in reader.lines() {
-        if let Ok(l) = line {
-            games.push(read_line_input(&l));
-        }
-    }
+    let games = parse_games(input);
 
     let part_1 = resolve_part_1(games.clone());
     info!("Part 1: {}", part_1);
@@ -1,14 +1,13 @@
 use core::fmt;
-use std::{
-    cmp::min,
-    fs::File,
-    io::{BufRead, BufReader},
-};
+use std::{cmp::min, fs, path::PathBuf, str::FromStr};
 
-use log::{debug, info};
+use log::{debug, info, trace};
+use tracing::{field::Empty, instrument, Span};
+
+use crate::{input::Input, render::escape_xml};
 
 #[derive(Eq, PartialEq, Clone, Debug, Copy)]
-struct Position {
+pub struct Position {
     row: usize,
     col: usize,
 }
@@ -23,10 +22,10 @@ struct MatrixNumber {
 impl MatrixNumber {
     fn overlap(&self, bounds: (Position, Position)) -> bool {
         let (start, end) = bounds;
-        return self.start_position.col <= end.col
+        self.start_position.col <= end.col
             && self.end_position.col >= start.col
             && self.start_position.row <= end.row
-            && self.end_position.row >= start.row;
+            && self.end_position.row >= start.row
     }
 }
 
@@ -40,78 +39,242 @@ impl fmt::Debug for MatrixNumber {
     }
 }
 
-fn is_symbol(c: char) -> bool {
-    return !(c.is_numeric() || c == '.');
+/// Characters that give meaning to the cells of an engine schematic
+#[derive(Clone, Debug)]
+pub struct SchematicRules {
+    /// Character used for empty cells
+    pub empty: char,
+    /// Characters counted as symbols, any non-digit non-empty cell when `None`
+    pub symbols: Option<Vec<char>>,
+    /// Character marking a potential gear
+    pub gear: char,
+    /// Number of adjacent part numbers a gear needs to be valid
+    pub gear_neighbours: usize,
+}
+
+impl Default for SchematicRules {
+    fn default() -> Self {
+        SchematicRules {
+            empty: '.',
+            symbols: None,
+            gear: '*',
+            gear_neighbours: 2,
+        }
+    }
+}
+
+impl SchematicRules {
+    fn is_symbol(&self, c: char) -> bool {
+        if c.is_ascii_digit() || c == self.empty {
+            return false;
+        }
+
+        match &self.symbols {
+            Some(symbols) => symbols.contains(&c),
+            None => true,
+        }
+    }
+
+    /// Only ASCII digits make up numbers; other cells must be the empty
+    /// character, a configured symbol or a printable ASCII character
+    fn is_valid_cell(&self, c: char) -> bool {
+        if c.is_ascii_digit() || c == self.empty || c.is_ascii_graphic() {
+            return true;
+        }
+
+        match &self.symbols {
+            Some(symbols) => symbols.contains(&c),
+            None => false,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum SchematicError {
+    Empty,
+    InvalidCell {
+        position: Position,
+        value: char,
+    },
+    RowLength {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// Run of digits starting at `position` that doesn't fit in a `usize`
+    NumberTooLarge {
+        position: Position,
+    },
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchematicError::Empty => write!(f, "Schematic is empty"),
+            SchematicError::InvalidCell { position, value } if value.is_numeric() => write!(
+                f,
+                "Non-ASCII digit {:?} (U+{:04X}) at row {}, column {}",
+                value,
+                *value as u32,
+                position.row + 1,
+                position.col + 1
+            ),
+            SchematicError::InvalidCell { position, value } => write!(
+                f,
+                "Invalid character {:?} (U+{:04X}) at row {}, column {}",
+                value,
+                *value as u32,
+                position.row + 1,
+                position.col + 1
+            ),
+            SchematicError::RowLength {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Row {} has {} cells, expected {}",
+                row + 1,
+                found,
+                expected
+            ),
+            SchematicError::NumberTooLarge { position } => write!(
+                f,
+                "Number at row {}, column {} exceeds {}",
+                position.row + 1,
+                position.col + 1,
+                usize::MAX
+            ),
+        }
+    }
+}
+
+/// Column of the first number of `row` that doesn't fit in a `usize`
+fn too_large_number(row: &[char]) -> Option<usize> {
+    let mut start = 0;
+
+    for (j, c) in row.iter().enumerate() {
+        if !c.is_ascii_digit() {
+            start = j + 1;
+        } else if row[start..=j]
+            .iter()
+            .collect::<String>()
+            .parse::<usize>()
+            .is_err()
+        {
+            return Some(start);
+        }
+    }
+
+    None
+}
+
+/// Parse the schematic into a grid of cells, checking every cell against the
+/// rules. Columns are counted in characters, not bytes.
+#[instrument(name = "parse", skip_all, fields(rows = Empty, cols = Empty))]
+fn parse_schematic(
+    grid: Vec<Vec<char>>,
+    rules: &SchematicRules,
+) -> Result<Vec<Vec<char>>, SchematicError> {
+    let mut matrix: Vec<Vec<char>> = vec![];
+
+    for (i, row) in grid.into_iter().enumerate() {
+        if let Some((j, value)) = row
+            .iter()
+            .enumerate()
+            .find(|(_, c)| !rules.is_valid_cell(**c))
+        {
+            return Err(SchematicError::InvalidCell {
+                position: Position { row: i, col: j },
+                value: *value,
+            });
+        }
+
+        if let Some(j) = too_large_number(&row) {
+            return Err(SchematicError::NumberTooLarge {
+                position: Position { row: i, col: j },
+            });
+        }
+
+        if let Some(first) = matrix.first() {
+            if first.len() != row.len() {
+                return Err(SchematicError::RowLength {
+                    row: i,
+                    expected: first.len(),
+                    found: row.len(),
+                });
+            }
+        }
+
+        matrix.push(row);
+    }
+
+    if matrix.is_empty() || matrix[0].is_empty() {
+        return Err(SchematicError::Empty);
+    }
+
+    Span::current().record("rows", matrix.len());
+    Span::current().record("cols", matrix[0].len());
+    Ok(matrix)
 }
 
 fn get_surroundings(
     start_position: Position,
     end_position: Position,
-    matrix: &Vec<Vec<char>>,
+    matrix: &[Vec<char>],
 ) -> Vec<char> {
     let mut surrounding: Vec<char> = vec![];
 
-    let horizontal_length = matrix[0].len() - 1;
-
-    if start_position.col > 0 {
-        let row = start_position.row.saturating_sub(1);
-        let from = start_position.col.saturating_sub(1);
-        let to = min(end_position.col + 1, horizontal_length);
+    let last_row = matrix.len() - 1;
+    let last_col = matrix[0].len() - 1;
+    let row = start_position.row;
+    let from = start_position.col.saturating_sub(1);
+    let to = min(end_position.col + 1, last_col);
 
-        let above = matrix[row][from..=to].to_vec();
-        surrounding.append(&mut above.clone());
-    } else {
-        debug!("No above");
+    if row > 0 {
+        let above = &matrix[row - 1][from..=to];
+        surrounding.extend_from_slice(above);
     }
 
-    if start_position.col < matrix.len() - 1 {
-        let row = min(start_position.row + 1, horizontal_length);
-        let from = start_position.col.saturating_sub(1);
-        let to = min(end_position.col + 1, horizontal_length);
-
-        let below = matrix[row][from..=to].to_vec();
-        surrounding.append(&mut below.clone());
-    } else {
-        debug!("No below");
+    if row < last_row {
+        let below = &matrix[row + 1][from..=to];
+        surrounding.extend_from_slice(below);
     }
 
-    let row = start_position.row;
-    debug!("col: {}", row);
-    if start_position.row > 0 {
-        let left = matrix[row][start_position.row.saturating_sub(1)];
-        debug!("Left: {}", left);
+    if start_position.col > 0 {
+        let left = matrix[row][start_position.col - 1];
         surrounding.push(left);
-    } else {
-        debug!("No left");
     }
 
-    if end_position.row < horizontal_length {
-        let y = min(end_position.row + 1, horizontal_length);
-        let right = matrix[row][y];
-        debug!("Right({}, {}): {}", row, y, right);
+    if end_position.col < last_col {
+        let right = matrix[row][end_position.col + 1];
         surrounding.push(right);
-    } else {
-        debug!("No right");
     }
 
-    return surrounding;
+    surrounding
 }
 
 /// Check if there is a sign around the given matrix_number
-fn has_sign_around(matrix_number: MatrixNumber, matrix: Vec<Vec<char>>) -> bool {
-    debug!("----");
-    debug!("Checking {:?}", matrix_number);
+fn has_sign_around(
+    matrix_number: MatrixNumber,
+    matrix: &[Vec<char>],
+    rules: &SchematicRules,
+) -> bool {
     let surrounding = get_surroundings(
         matrix_number.start_position,
         matrix_number.end_position,
-        &matrix,
+        matrix,
     );
 
-    surrounding.into_iter().any(|el| is_symbol(el))
+    surrounding.into_iter().any(|el| rules.is_symbol(el))
 }
 
-fn add_nb_stack(stack: &mut Vec<char>, numbers: &mut Vec<MatrixNumber>, i: usize, j: usize) {
-    let value = stack.iter().collect::<String>().parse().unwrap();
+fn add_nb_stack(stack: &[char], numbers: &mut Vec<MatrixNumber>, i: usize, j: usize) {
+    let value = stack
+        .iter()
+        .collect::<String>()
+        .parse()
+        .expect("Numbers are checked to fit when parsing");
     numbers.push(MatrixNumber {
         value,
         start_position: Position {
@@ -122,7 +285,11 @@ fn add_nb_stack(stack: &mut Vec<char>, numbers: &mut Vec<MatrixNumber>, i: usize
     })
 }
 
-fn has_part_number_around(gear: Position, numbers: Vec<MatrixNumber>) -> usize {
+fn has_part_number_around(
+    gear: Position,
+    numbers: &[MatrixNumber],
+    rules: &SchematicRules,
+) -> usize {
     let top_left = Position {
         row: gear.row.saturating_sub(1),
         col: gear.col.saturating_sub(1),
@@ -134,28 +301,30 @@ fn has_part_number_around(gear: Position, numbers: Vec<MatrixNumber>) -> usize {
     };
 
     let surrounds: Vec<usize> = numbers
-        .into_iter()
-        .filter_map(|nb| {
-            if nb.overlap((top_left, bottom_right)) {
-                return Some(nb.value);
-            }
-            return None;
-        })
+        .iter()
+        .filter(|nb| nb.overlap((top_left, bottom_right)))
+        .map(|nb| nb.value)
         .collect();
 
-    debug!(
+    trace!(
         "Gear({}, {}) - Surrounds: {:?}",
-        gear.col, gear.row, surrounds
+        gear.col,
+        gear.row,
+        surrounds
     );
 
-    if surrounds.len() < 2 {
+    if surrounds.len() != rules.gear_neighbours {
         return 0;
     }
 
-    return surrounds.iter().fold(1, |acc, el| acc * el);
+    surrounds.iter().product()
 }
 
-fn extract_part_numbers(matrix: &Vec<Vec<char>>) -> (Vec<MatrixNumber>, Vec<Position>) {
+#[instrument(name = "extract", skip_all, fields(numbers = Empty, gears = Empty))]
+fn extract_part_numbers(
+    matrix: &[Vec<char>],
+    rules: &SchematicRules,
+) -> (Vec<MatrixNumber>, Vec<Position>) {
     let mut matrix_numbers: Vec<MatrixNumber> = vec![];
     let mut gears: Vec<Position> = vec![];
 
@@ -163,68 +332,543 @@ fn extract_part_numbers(matrix: &Vec<Vec<char>>) -> (Vec<MatrixNumber>, Vec<Posi
         let mut number_stack: Vec<char> = vec![];
 
         for (j, col) in row.iter().enumerate() {
-            if col.is_numeric() {
+            if col.is_ascii_digit() {
                 number_stack.push(*col);
 
                 if let Some(next) = row.get(j + 1) {
-                    if !next.is_numeric() {
-                        add_nb_stack(&mut number_stack, &mut matrix_numbers, i, j);
+                    if !next.is_ascii_digit() {
+                        add_nb_stack(&number_stack, &mut matrix_numbers, i, j);
                         number_stack = vec![];
                     }
                 } else {
-                    add_nb_stack(&mut number_stack, &mut matrix_numbers, i, j);
+                    add_nb_stack(&number_stack, &mut matrix_numbers, i, j);
                 }
             } else {
-                if col.eq(&'*') {
+                if *col == rules.gear {
                     gears.push(Position { row: i, col: j });
                 }
                 // Reset stack
                 number_stack = vec![];
             }
         }
-        debug!("{:?}", row);
     }
 
-    return (matrix_numbers, gears);
+    Span::current().record("numbers", matrix_numbers.len());
+    Span::current().record("gears", gears.len());
+    (matrix_numbers, gears)
 }
 
-pub fn run() {
-    let filename = "./src/inputs/day_3.txt";
-    let file = File::open(filename).expect("Couldn't read file");
-    let reader = BufReader::new(file);
+/// Sum of the numbers next to a symbol
+#[instrument(name = "part_1", skip_all, fields(part_numbers = Empty))]
+fn resolve_part_1(
+    matrix_numbers: &[MatrixNumber],
+    matrix: &[Vec<char>],
+    rules: &SchematicRules,
+) -> usize {
+    debug!("Matrix numbers: {:?}", matrix_numbers);
 
-    let mut matrix: Vec<Vec<char>> = vec![];
+    let part_numbers: Vec<usize> = matrix_numbers
+        .iter()
+        .filter(|nb| has_sign_around(**nb, matrix, rules))
+        .map(|nb| nb.value)
+        .collect();
+
+    Span::current().record("part_numbers", part_numbers.len());
+    part_numbers.iter().sum::<usize>()
+}
+
+/// Sum of the gear ratios
+#[instrument(name = "part_2", skip_all, fields(gears = gears.len()))]
+fn resolve_part_2(
+    gears: Vec<Position>,
+    matrix_numbers: &[MatrixNumber],
+    rules: &SchematicRules,
+) -> usize {
+    debug!("Gears: {:?}", gears);
+
+    gears
+        .into_iter()
+        .map(|gear| has_part_number_around(gear, matrix_numbers, rules))
+        .sum::<usize>()
+}
+
+/// Compute both parts of the puzzle for the given schematic
+fn solve(matrix: &[Vec<char>], rules: &SchematicRules) -> (usize, usize) {
+    let (matrix_numbers, gears) = extract_part_numbers(matrix, rules);
+
+    (
+        resolve_part_1(&matrix_numbers, matrix, rules),
+        resolve_part_2(gears, &matrix_numbers, rules),
+    )
+}
 
-    for line in reader.lines() {
-        if let Ok(l) = line {
-            matrix.push(l.chars().collect());
+/// What a cell of the schematic turned out to be once the puzzle is solved
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+enum CellKind {
+    Empty,
+    PartNumber,
+    Number,
+    Symbol,
+    Gear,
+}
+
+impl CellKind {
+    fn ansi_color(&self) -> &'static str {
+        match self {
+            CellKind::Empty => "\x1b[2m",
+            CellKind::PartNumber => "\x1b[1;32m",
+            CellKind::Number => "\x1b[31m",
+            CellKind::Symbol => "\x1b[1;33m",
+            CellKind::Gear => "\x1b[1;30;46m",
         }
     }
 
-    let (matrix_numbers, gears) = extract_part_numbers(&matrix);
+    fn css_class(&self) -> &'static str {
+        match self {
+            CellKind::Empty => "empty",
+            CellKind::PartNumber => "part",
+            CellKind::Number => "number",
+            CellKind::Symbol => "symbol",
+            CellKind::Gear => "gear",
+        }
+    }
 
-    // Part 1
-    debug!("Matrix numbers: {:?}", matrix_numbers);
+    fn svg_color(&self) -> &'static str {
+        match self {
+            CellKind::Empty => "#9e9e9e",
+            CellKind::PartNumber => "#2e7d32",
+            CellKind::Number => "#c62828",
+            CellKind::Symbol => "#f9a825",
+            CellKind::Gear => "#00838f",
+        }
+    }
+}
 
-    let number_with_sign: Vec<MatrixNumber> = matrix_numbers
-        .clone()
-        .into_iter()
-        .filter(|nb| has_sign_around(nb.clone(), matrix.clone()))
+/// Output format of the schematic rendering
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum RenderFormat {
+    Ansi,
+    Svg,
+    Html,
+}
+
+impl FromStr for RenderFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(RenderFormat::Ansi),
+            "svg" => Ok(RenderFormat::Svg),
+            "html" => Ok(RenderFormat::Html),
+            _ => Err(format!("Unknown render format: {}", s)),
+        }
+    }
+}
+
+/// Tag every cell of the schematic with the role it plays in the solution
+fn classify(matrix: &[Vec<char>], rules: &SchematicRules) -> Vec<Vec<CellKind>> {
+    let (matrix_numbers, gears) = extract_part_numbers(matrix, rules);
+
+    let mut kinds: Vec<Vec<CellKind>> = matrix
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|c| {
+                    if rules.is_symbol(*c) {
+                        CellKind::Symbol
+                    } else {
+                        CellKind::Empty
+                    }
+                })
+                .collect()
+        })
         .collect();
 
-    let sum = number_with_sign
-        .into_iter()
-        .map(|nb| nb.value)
-        .sum::<usize>();
+    for nb in &matrix_numbers {
+        let kind = if has_sign_around(*nb, matrix, rules) {
+            CellKind::PartNumber
+        } else {
+            CellKind::Number
+        };
+
+        kinds[nb.start_position.row][nb.start_position.col..=nb.end_position.col].fill(kind);
+    }
 
-    // Part 2
-    debug!("Gears: {:?}", gears);
-    let mut gears_surrounds: Vec<usize> = vec![];
     for gear in gears {
-        let nb = has_part_number_around(gear, matrix_numbers.clone());
-        gears_surrounds.push(nb);
+        if has_part_number_around(gear, &matrix_numbers, rules) > 0 {
+            kinds[gear.row][gear.col] = CellKind::Gear;
+        }
+    }
+
+    kinds
+}
+
+fn render_ansi(matrix: &[Vec<char>], kinds: &[Vec<CellKind>]) -> String {
+    let mut output = String::new();
+
+    for (row, row_kinds) in matrix.iter().zip(kinds) {
+        for (c, kind) in row.iter().zip(row_kinds) {
+            output.push_str(&format!("{}{}\x1b[0m", kind.ansi_color(), c));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+fn render_svg(matrix: &[Vec<char>], kinds: &[Vec<CellKind>]) -> String {
+    const CELL_WIDTH: usize = 10;
+    const CELL_HEIGHT: usize = 16;
+
+    let width = matrix.iter().map(|row| row.len()).max().unwrap_or(0) * CELL_WIDTH;
+    let height = matrix.len() * CELL_HEIGHT;
+
+    let mut output = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"14\">\n",
+        width, height
+    );
+    output.push_str(&format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>\n",
+        width, height
+    ));
+
+    for (i, (row, row_kinds)) in matrix.iter().zip(kinds).enumerate() {
+        for (j, (c, kind)) in row.iter().zip(row_kinds).enumerate() {
+            let x = j * CELL_WIDTH;
+            let y = i * CELL_HEIGHT;
+
+            if *kind == CellKind::Gear {
+                output.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#b2ebf2\"/>\n",
+                    x, y, CELL_WIDTH, CELL_HEIGHT
+                ));
+            }
+
+            output.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\" class=\"{}\">{}</text>\n",
+                x + 1,
+                y + CELL_HEIGHT - 4,
+                kind.svg_color(),
+                kind.css_class(),
+                escape_xml(&c.to_string())
+            ));
+        }
+    }
+
+    output.push_str("</svg>\n");
+    output
+}
+
+fn render_html(matrix: &[Vec<char>], kinds: &[Vec<CellKind>]) -> String {
+    let kinds_list = [
+        CellKind::Empty,
+        CellKind::PartNumber,
+        CellKind::Number,
+        CellKind::Symbol,
+        CellKind::Gear,
+    ];
+
+    let mut output = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Day 3 - Engine schematic</title>\n<style>\n",
+    );
+    for kind in kinds_list {
+        output.push_str(&format!(
+            ".{} {{ color: {}; }}\n",
+            kind.css_class(),
+            kind.svg_color()
+        ));
+    }
+    output.push_str(".gear { background: #b2ebf2; font-weight: bold; }\n");
+    output.push_str("</style>\n</head>\n<body>\n<p>");
+    for kind in kinds_list {
+        output.push_str(&format!(
+            "<span class=\"{0}\">{0}</span> ",
+            kind.css_class()
+        ));
+    }
+    output.push_str("</p>\n<pre>\n");
+
+    for (row, row_kinds) in matrix.iter().zip(kinds) {
+        for (c, kind) in row.iter().zip(row_kinds) {
+            output.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                kind.css_class(),
+                escape_xml(&c.to_string())
+            ));
+        }
+        output.push('\n');
+    }
+
+    output.push_str("</pre>\n</body>\n</html>\n");
+    output
+}
+
+/// Render the schematic with part numbers, other numbers, symbols and valid
+/// gears highlighted
+fn render(matrix: &[Vec<char>], rules: &SchematicRules, format: RenderFormat) -> String {
+    let kinds = classify(matrix, rules);
+
+    match format {
+        RenderFormat::Ansi => render_ansi(matrix, &kinds),
+        RenderFormat::Svg => render_svg(matrix, &kinds),
+        RenderFormat::Html => render_html(matrix, &kinds),
+    }
+}
+
+pub fn answers(input: &Input) -> Vec<(u8, String)> {
+    let rules = SchematicRules::default();
+    let matrix = parse_schematic(input.grid(), &rules).unwrap_or_else(|err| panic!("{}", err));
+    let (part_1, part_2) = solve(&matrix, &rules);
+
+    vec![(1, part_1.to_string()), (2, part_2.to_string())]
+}
+
+pub fn run(input: &Input, render_format: Option<RenderFormat>, output: Option<PathBuf>) {
+    let rules = SchematicRules::default();
+    let matrix = match parse_schematic(input.grid(), &rules) {
+        Ok(matrix) => matrix,
+        Err(err) => panic!("Invalid schematic {}: {}", input.name(), err),
+    };
+
+    let (part_1, part_2) = solve(&matrix, &rules);
+
+    info!("Part 1: {}", part_1);
+    info!("Part 2: {}", part_2);
+
+    if let Some(format) = render_format {
+        let rendering = render(&matrix, &rules, format);
+
+        match output {
+            Some(path) => {
+                fs::write(&path, rendering).expect("Couldn't write rendering");
+                info!("Rendering written to {}", path.display());
+            }
+            None => print!("{}", rendering),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_matrix(text: &str, rules: &SchematicRules) -> Vec<Vec<char>> {
+        parse_schematic(Input::new("test", text).grid(), rules).unwrap()
+    }
+
+    const SCHEMATIC: &str = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+    #[test]
+    fn test_default_rules() {
+        let matrix = parse_matrix(SCHEMATIC, &SchematicRules::default());
+        assert_eq!(solve(&matrix, &SchematicRules::default()), (4361, 467835));
+    }
+
+    #[test]
+    fn test_non_square_grids() {
+        let rules = SchematicRules::default();
+
+        // 5 only touches the # diagonally, on the last row of a wide grid
+        let wide = parse_matrix("..........\n.3......5.\n.......#..", &rules);
+        assert_eq!(solve(&wide, &rules).0, 5);
+
+        let tall = parse_matrix("1.\n*.\n..\n.2\n..\n.#", &rules);
+        assert_eq!(solve(&tall, &rules).0, 1);
+    }
+
+    #[test]
+    fn test_first_column() {
+        let rules = SchematicRules::default();
+
+        let above = parse_matrix("*...\n12..\n....", &rules);
+        assert_eq!(solve(&above, &rules).0, 12);
+
+        let below = parse_matrix("12..\n.#..\n34..", &rules);
+        assert_eq!(solve(&below, &rules).0, 12 + 34);
+
+        let right = parse_matrix("12*.\n....\n7...", &rules);
+        assert_eq!(solve(&right, &rules).0, 12);
+    }
+
+    #[test]
+    fn test_restricted_symbols() {
+        let rules = SchematicRules {
+            symbols: Some(vec!['*']),
+            ..SchematicRules::default()
+        };
+        let matrix = parse_matrix(SCHEMATIC, &rules);
+
+        // 592 (next to +), 633 (next to #) and 664 (next to $) no longer count
+        assert_eq!(solve(&matrix, &rules).0, 4361 - 592 - 633 - 664);
+    }
+
+    #[test]
+    fn test_custom_empty_cell() {
+        let rules = SchematicRules {
+            empty: ' ',
+            ..SchematicRules::default()
+        };
+        let matrix = parse_matrix(&SCHEMATIC.replace('.', " "), &rules);
+        assert_eq!(solve(&matrix, &rules), (4361, 467835));
+
+        // With the default rules, every blank is now a symbol
+        let (part_1, _) = solve(&matrix, &SchematicRules::default());
+        assert_eq!(
+            part_1,
+            467 + 114 + 35 + 633 + 617 + 58 + 592 + 755 + 664 + 598
+        );
+    }
+
+    #[test]
+    fn test_custom_gear() {
+        let rules = SchematicRules {
+            gear: '@',
+            ..SchematicRules::default()
+        };
+        let matrix = parse_matrix(&SCHEMATIC.replace('*', "@"), &rules);
+        assert_eq!(solve(&matrix, &rules), (4361, 467835));
+
+        let (_, part_2) = solve(&matrix, &SchematicRules::default());
+        assert_eq!(part_2, 0);
     }
 
-    info!("Part 1: {}", sum);
-    info!("Part 2: {}", gears_surrounds.iter().sum::<usize>());
+    #[test]
+    fn test_gear_neighbours() {
+        let rules = SchematicRules {
+            gear_neighbours: 1,
+            ..SchematicRules::default()
+        };
+        let matrix = parse_matrix(SCHEMATIC, &rules);
+
+        // Only the gear next to 617 has a single neighbour
+        assert_eq!(solve(&matrix, &rules).1, 617);
+    }
+
+    #[test]
+    fn test_classify() {
+        let matrix = parse_matrix(SCHEMATIC, &SchematicRules::default());
+        let kinds = classify(&matrix, &SchematicRules::default());
+
+        // 467 is a part number, 114 is not
+        assert_eq!(kinds[0][0..3], [CellKind::PartNumber; 3]);
+        assert_eq!(kinds[0][5..8], [CellKind::Number; 3]);
+        assert_eq!(kinds[0][3], CellKind::Empty);
+
+        // Only the stars with two part numbers around are gears
+        assert_eq!(kinds[1][3], CellKind::Gear);
+        assert_eq!(kinds[4][3], CellKind::Symbol);
+        assert_eq!(kinds[8][5], CellKind::Gear);
+        assert_eq!(kinds[3][6], CellKind::Symbol);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let matrix = parse_matrix(SCHEMATIC, &SchematicRules::default());
+        let rules = SchematicRules::default();
+
+        let ansi = render(&matrix, &rules, RenderFormat::Ansi);
+        assert_eq!(ansi.lines().count(), 10);
+        assert!(ansi.starts_with("\x1b[1;32m4\x1b[0m"));
+
+        let svg = render(&matrix, &rules, RenderFormat::Svg);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("class=\"gear\"").count(), 2);
+
+        let html = render(&matrix, &rules, RenderFormat::Html);
+        assert!(html.contains("<span class=\"symbol\">$</span>"));
+    }
+
+    #[test]
+    fn test_parse_non_ascii_digit() {
+        let text = "467..\n..\u{663}*.\n.35..";
+        let err = parse_schematic(Input::new("test", text).grid(), &SchematicRules::default())
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            SchematicError::InvalidCell {
+                position: Position { row: 1, col: 2 },
+                value: '\u{663}',
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Non-ASCII digit '\u{663}' (U+0663) at row 2, column 3"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_cell() {
+        let text = "467..\n.é.*.\n.35..\n.....\n.....";
+        let err = parse_schematic(Input::new("test", text).grid(), &SchematicRules::default())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid character 'é' (U+00E9) at row 2, column 2"
+        );
+
+        // Explicitly configured symbols may be outside of ASCII
+        let rules = SchematicRules {
+            symbols: Some(vec!['é', '*']),
+            ..SchematicRules::default()
+        };
+        let matrix = parse_matrix(text, &rules);
+        assert_eq!(solve(&matrix, &rules), (467 + 35, 467 * 35));
+    }
+
+    #[test]
+    fn test_parse_number_too_large() {
+        let text = "1234567890123456789012345*\n..........................";
+        let err = parse_schematic(Input::new("test", text).grid(), &SchematicRules::default())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            SchematicError::NumberTooLarge {
+                position: Position { row: 0, col: 0 }
+            }
+        );
+
+        // usize::MAX itself still fits
+        let number = format!(".{}.", usize::MAX);
+        let text = format!("*{}\n{}", ".".repeat(number.len() - 1), number);
+        let matrix = parse_matrix(&text, &SchematicRules::default());
+        assert_eq!(solve(&matrix, &SchematicRules::default()).0, usize::MAX);
+
+        let text = format!(".{}0.", usize::MAX);
+        let err = parse_schematic(Input::new("test", &text).grid(), &SchematicRules::default())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Number at row 1, column 2 exceeds {}", usize::MAX)
+        );
+    }
+
+    #[test]
+    fn test_parse_shape() {
+        let err = parse_schematic(
+            Input::new("test", "467..\n..*\n.35..").grid(),
+            &SchematicRules::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            SchematicError::RowLength {
+                row: 1,
+                expected: 5,
+                found: 3,
+            }
+        );
+
+        let err = parse_schematic(vec![], &SchematicRules::default()).unwrap_err();
+        assert_eq!(err, SchematicError::Empty);
+    }
 }
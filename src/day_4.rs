@@ -1,130 +1,540 @@
+use core::fmt;
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    str::FromStr,
 };
 
 use log::{debug, info};
-use regex::Regex;
+use tracing::{field::Empty, instrument, Span};
+
+use crate::input::Input;
+
+/// Winning numbers of a card, as a bitset when they all fit in `0..BITSET_SIZE`
+#[derive(Eq, PartialEq, Clone, Debug)]
+enum WinningSet {
+    Bits(u128),
+    Hashed(HashSet<usize>),
+}
+
+impl WinningSet {
+    const BITSET_SIZE: usize = 100;
+
+    fn new(winnings: &[usize]) -> Self {
+        if winnings.iter().all(|n| *n < Self::BITSET_SIZE) {
+            WinningSet::Bits(winnings.iter().fold(0, |bits, n| bits | (1 << n)))
+        } else {
+            WinningSet::Hashed(winnings.iter().copied().collect())
+        }
+    }
+
+    fn contains(&self, n: usize) -> bool {
+        match self {
+            WinningSet::Bits(bits) => n < Self::BITSET_SIZE && bits & (1 << n) != 0,
+            WinningSet::Hashed(set) => set.contains(&n),
+        }
+    }
+}
 
 #[derive(Eq, PartialEq, Clone, Debug)]
 struct Card {
     id: usize,
     winnings: Vec<usize>,
     numbers: Vec<usize>,
+    /// How many of `numbers` are winning numbers
+    matches: usize,
 }
 
 impl Card {
-    fn points(&self) -> usize {
-        let mut points = 0;
-        for number in &self.numbers {
-            if self.winnings.contains(number) {
-                if points == 0 {
-                    points = 1
-                } else {
-                    points *= 2;
-                }
-            }
+    fn new(id: usize, winnings: Vec<usize>, numbers: Vec<usize>) -> Self {
+        let winning_set = WinningSet::new(&winnings);
+        let matches = numbers.iter().filter(|n| winning_set.contains(**n)).count();
+
+        Card {
+            id,
+            winnings,
+            numbers,
+            matches,
         }
-        return points;
     }
 
-    fn winning_copies(&self) -> Vec<usize> {
-        return self
-            .numbers
-            .iter()
-            .filter(|n| self.winnings.contains(n))
-            .enumerate()
-            .map(|(i, _n)| i + 1 + self.id)
-            .collect();
+    fn points(&self) -> Result<usize, CardError> {
+        match self.matches {
+            0 => Ok(0),
+            matches => 1_usize
+                .checked_shl((matches - 1) as u32)
+                .ok_or(CardError::PointsOverflow { card: self.id }),
+        }
+    }
+
+    /// Ids of the cards this card wins a copy of, in a table of `table_size`
+    /// cards
+    fn winning_copies(
+        &self,
+        table_size: usize,
+        past_end: PastEndPolicy,
+    ) -> Result<Vec<usize>, CardError> {
+        let last_copy = self.id + self.matches;
+
+        if last_copy > table_size && past_end == PastEndPolicy::Error {
+            return Err(CardError::CopyPastEnd {
+                card: self.id,
+                copy: last_copy,
+                table_size,
+            });
+        }
+
+        Ok((self.id + 1..=last_copy.min(table_size)).collect())
     }
 }
 
-fn parse_card(text: &str) -> Card {
-    let re = Regex::new(r"(Card +(?P<id>(\d+)): )").unwrap();
-    let id = re.captures(text).unwrap().name("id").unwrap().as_str();
+/// What to do when a card wins copies of cards past the end of the table
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub enum PastEndPolicy {
+    /// Copies of cards that don't exist are ignored
+    #[default]
+    Clamp,
+    /// Copies of cards that don't exist are an error
+    Error,
+}
 
-    let new_text = text.replace(re.captures(text).unwrap().get(0).unwrap().as_str(), "");
+impl FromStr for PastEndPolicy {
+    type Err = String;
 
-    let mut card = Card {
-        id: id.parse::<usize>().unwrap(),
-        winnings: vec![],
-        numbers: vec![],
-    };
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(PastEndPolicy::Clamp),
+            "error" => Ok(PastEndPolicy::Error),
+            _ => Err(format!("Unknown past end policy: {}", s)),
+        }
+    }
+}
 
-    let blocks: Vec<String> = new_text.split("|").map(|s| s.trim().to_string()).collect();
-    if let Some(winnings) = blocks.first() {
-        card.winnings = winnings
-            .split(" ")
-            .filter_map(|s| s.parse::<usize>().ok())
-            .collect();
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum CardError {
+    MissingPrefix,
+    MissingSeparator(char),
+    InvalidId {
+        token: String,
+        column: usize,
+    },
+    InvalidNumber {
+        token: String,
+        column: usize,
+    },
+    DuplicateWinning {
+        number: usize,
+        column: usize,
+    },
+    UnexpectedId {
+        expected: usize,
+        found: usize,
+    },
+    CopyPastEnd {
+        card: usize,
+        copy: usize,
+        table_size: usize,
+    },
+    /// More points than a `usize` can count
+    PointsOverflow {
+        card: usize,
+    },
+    /// More copies of a card than a `u128` can count
+    CopiesOverflow {
+        card: usize,
+    },
+}
+
+impl fmt::Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardError::MissingPrefix => write!(f, "Line doesn't start with \"Card\""),
+            CardError::MissingSeparator(separator) => write!(f, "Missing '{}'", separator),
+            CardError::InvalidId { token, column } => {
+                write!(f, "Invalid card id {:?} at column {}", token, column)
+            }
+            CardError::InvalidNumber { token, column } => {
+                write!(f, "Invalid number {:?} at column {}", token, column)
+            }
+            CardError::DuplicateWinning { number, column } => {
+                write!(
+                    f,
+                    "Duplicate winning number {} at column {}",
+                    number, column
+                )
+            }
+            CardError::UnexpectedId { expected, found } => {
+                write!(f, "Expected card {}, found card {}", expected, found)
+            }
+            CardError::CopyPastEnd {
+                card,
+                copy,
+                table_size,
+            } => write!(
+                f,
+                "Card {} wins a copy of card {} but there are only {} cards",
+                card, copy, table_size
+            ),
+            CardError::PointsOverflow { card } => {
+                write!(f, "Points of card {} overflow", card)
+            }
+            CardError::CopiesOverflow { card } => {
+                write!(f, "Copy count of card {} overflows", card)
+            }
+        }
     }
+}
 
-    if let Some(numbers) = blocks.last() {
-        card.numbers = numbers
-            .split(" ")
-            .filter_map(|s| s.parse::<usize>().ok())
-            .collect();
+/// Space separated tokens of `text`, along with their column in the line
+/// given the column `text` starts at
+fn tokens(text: &str, start_column: usize) -> impl Iterator<Item = (usize, &str)> {
+    text.split(' ')
+        .scan(start_column, |column, token| {
+            let token_column = *column;
+            *column += token.chars().count() + 1;
+            Some((token_column, token))
+        })
+        .filter(|(_, token)| !token.is_empty())
+}
+
+fn parse_number(token: &str) -> Option<usize> {
+    if token.bytes().all(|b| b.is_ascii_digit()) {
+        token.parse().ok()
+    } else {
+        None
     }
+}
 
-    return card;
+fn parse_numbers(text: &str, start_column: usize) -> Result<Vec<usize>, CardError> {
+    tokens(text, start_column)
+        .map(|(column, token)| {
+            parse_number(token).ok_or_else(|| CardError::InvalidNumber {
+                token: token.to_string(),
+                column,
+            })
+        })
+        .collect()
 }
 
-fn run_part_2(cards: Vec<Card>) {
-    let mut copies: HashMap<usize, usize> = HashMap::new();
+/// Parse a `Card N: winning numbers | numbers` line
+fn parse_card(text: &str) -> Result<Card, CardError> {
+    let rest = text.strip_prefix("Card").ok_or(CardError::MissingPrefix)?;
+    let (id_text, rest) = rest
+        .split_once(':')
+        .ok_or(CardError::MissingSeparator(':'))?;
+    let (winnings_text, numbers_text) = rest
+        .split_once('|')
+        .ok_or(CardError::MissingSeparator('|'))?;
+
+    let id_column = "Card".len() + 1;
+    let winnings_column = id_column + id_text.chars().count() + 1;
+    let numbers_column = winnings_column + winnings_text.chars().count() + 1;
+
+    let invalid_id = |column| CardError::InvalidId {
+        token: id_text.trim().to_string(),
+        column,
+    };
+    let mut id_tokens = tokens(id_text, id_column);
+    let id = match (id_text.starts_with(' '), id_tokens.next(), id_tokens.next()) {
+        (true, Some((column, token)), None) => {
+            parse_number(token).ok_or_else(|| invalid_id(column))?
+        }
+        // Point at the first token of the id, or right after "Card" when there is none
+        (_, first, _) => {
+            return Err(invalid_id(first.map_or(id_column, |(column, _)| column)));
+        }
+    };
+
+    let mut winnings: Vec<usize> = vec![];
+    for (column, token) in tokens(winnings_text, winnings_column) {
+        let number = parse_number(token).ok_or_else(|| CardError::InvalidNumber {
+            token: token.to_string(),
+            column,
+        })?;
 
-    for card in cards.clone() {
-        debug!("------------------");
-        debug!("copies: {:?}", copies);
-        debug!("Card: {:?}", card.id);
+        if winnings.contains(&number) {
+            return Err(CardError::DuplicateWinning { number, column });
+        }
+        winnings.push(number);
+    }
 
-        let winings_card = card.winning_copies();
-        debug!("Winnings: {:?}", winings_card);
+    let numbers = parse_numbers(numbers_text, numbers_column)?;
 
-        if let Some(card_copies) = copies.get(&card.id) {
-            for _n in 1..=*card_copies {
-                for card_copy_id in winings_card.clone() {
-                    copies
-                        .entry(card_copy_id)
-                        .and_modify(|c| *c += 1)
-                        .or_insert(1);
-                }
-            }
+    Ok(Card::new(id, winnings, numbers))
+}
+
+/// Parse every card of the table, making sure they are numbered from 1
+/// without gaps as part 2 relies on it. Errors come with their line number.
+#[instrument(name = "parse", skip_all, fields(cards = Empty))]
+fn parse_cards<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<Card>, (usize, CardError)> {
+    let mut cards: Vec<Card> = vec![];
+
+    for (i, line) in lines.into_iter().enumerate() {
+        let card = parse_card(line).map_err(|err| (i + 1, err))?;
+
+        let expected = cards.len() + 1;
+        if card.id != expected {
+            return Err((
+                i + 1,
+                CardError::UnexpectedId {
+                    expected,
+                    found: card.id,
+                },
+            ));
+        }
+
+        cards.push(card);
+    }
+
+    Span::current().record("cards", cards.len());
+    Ok(cards)
+}
+
+/// Sum of the points of every card
+#[instrument(name = "part_1", skip_all, fields(winning_cards = Empty))]
+fn run_part_1(cards: &[Card]) -> Result<usize, CardError> {
+    let winning_cards = cards.iter().filter(|c| c.matches > 0).count();
+
+    Span::current().record("winning_cards", winning_cards);
+    cards.iter().try_fold(0_usize, |total, card| {
+        total
+            .checked_add(card.points()?)
+            .ok_or(CardError::PointsOverflow { card: card.id })
+    })
+}
+
+/// Total number of scratchcards once every won copy has been processed.
+///
+/// Each card is visited once: all of its copies win the same following cards,
+/// so they are handed over in bulk rather than one copy at a time.
+#[instrument(name = "part_2", skip_all, fields(cards = cards.len()))]
+fn run_part_2(cards: &[Card], past_end: PastEndPolicy) -> Result<u128, CardError> {
+    let mut won_copies: HashMap<usize, u128> = HashMap::new();
+    let mut total: u128 = 0;
+
+    for card in cards {
+        let overflow = CardError::CopiesOverflow { card: card.id };
+        let card_copies = won_copies
+            .get(&card.id)
+            .copied()
+            .unwrap_or(0)
+            .checked_add(1)
+            .ok_or(overflow.clone())?;
+        total = total.checked_add(card_copies).ok_or(overflow.clone())?;
+
+        let winnings_card = card.winning_copies(cards.len(), past_end)?;
+        debug!(
+            "Card {}: {} copies, wins {:?}",
+            card.id, card_copies, winnings_card
+        );
+
+        for card_copy_id in winnings_card {
+            let copies = won_copies.entry(card_copy_id).or_insert(0);
+            *copies = copies
+                .checked_add(card_copies)
+                .ok_or(CardError::CopiesOverflow { card: card_copy_id })?;
         }
+    }
+
+    Ok(total)
+}
 
-        copies.entry(card.id).and_modify(|c| *c += 1).or_insert(1);
+/// How a card's copies came to be during the part 2 cascade
+#[derive(Eq, PartialEq, Clone, Debug)]
+struct CascadeRow {
+    id: usize,
+    matches: usize,
+    /// Copies won from earlier cards, as `(card id, copies)`
+    received: Vec<(usize, u128)>,
+    /// Original card plus every copy received
+    copies: u128,
+    /// Number of cards processed so far, this one included
+    running_total: u128,
+}
 
-        for card_copy_id in winings_card.clone() {
-            copies
+/// Replay the part 2 cascade, keeping track of where every copy came from
+fn cascade(cards: &[Card], past_end: PastEndPolicy) -> Result<Vec<CascadeRow>, CardError> {
+    let mut received: HashMap<usize, Vec<(usize, u128)>> = HashMap::new();
+    let mut running_total: u128 = 0;
+    let mut rows: Vec<CascadeRow> = Vec::with_capacity(cards.len());
+
+    for card in cards {
+        let card_received = received.remove(&card.id).unwrap_or_default();
+        let overflow = CardError::CopiesOverflow { card: card.id };
+        let copies = card_received
+            .iter()
+            .try_fold(1_u128, |copies, (_id, c)| copies.checked_add(*c))
+            .ok_or(overflow.clone())?;
+        running_total = running_total.checked_add(copies).ok_or(overflow)?;
+
+        for card_copy_id in card.winning_copies(cards.len(), past_end)? {
+            received
                 .entry(card_copy_id)
-                .and_modify(|c| *c += 1)
-                .or_insert(1);
+                .or_default()
+                .push((card.id, copies));
         }
+
+        rows.push(CascadeRow {
+            id: card.id,
+            matches: card.matches,
+            received: card_received,
+            copies,
+            running_total,
+        });
     }
 
-    let total = copies.iter().map(|(_k, v)| v).sum::<usize>();
-    info!("Part 2: {}", total);
+    Ok(rows)
 }
 
-pub fn run() {
-    info!("--- DAY 4 ----");
+/// Output format of the part 2 cascade report
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum ExplainFormat {
+    Table,
+    Csv,
+}
 
-    let filename = "./src/inputs/day_4.txt";
-    let file = File::open(filename).expect("Couldn't read file");
-    let reader = BufReader::new(file);
+impl FromStr for ExplainFormat {
+    type Err = String;
 
-    let mut cards: Vec<Card> = vec![];
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(ExplainFormat::Table),
+            "csv" => Ok(ExplainFormat::Csv),
+            _ => Err(format!("Unknown explain format: {}", s)),
+        }
+    }
+}
 
-    for line in reader.lines() {
-        if let Ok(l) = line {
-            cards.push(parse_card(&l))
+fn explain(rows: &[CascadeRow], format: ExplainFormat) -> String {
+    let received_text = |row: &CascadeRow, separator: &str| {
+        row.received
+            .iter()
+            .map(|(id, copies)| format!("{}:{}", id, copies))
+            .collect::<Vec<String>>()
+            .join(separator)
+    };
+
+    match format {
+        ExplainFormat::Csv => {
+            let mut output = String::from("card,matches,received_from,copies,running_total\n");
+            for row in rows {
+                output.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    row.id,
+                    row.matches,
+                    received_text(row, ";"),
+                    row.copies,
+                    row.running_total
+                ));
+            }
+            output
+        }
+        ExplainFormat::Table => {
+            let header = ["Card", "Matches", "Received from", "Copies", "Total"];
+            let lines: Vec<[String; 5]> = rows
+                .iter()
+                .map(|row| {
+                    [
+                        row.id.to_string(),
+                        row.matches.to_string(),
+                        received_text(row, " "),
+                        row.copies.to_string(),
+                        row.running_total.to_string(),
+                    ]
+                })
+                .collect();
+
+            let widths: Vec<usize> = (0..header.len())
+                .map(|i| {
+                    lines
+                        .iter()
+                        .map(|line| line[i].len())
+                        .chain([header[i].len()])
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+
+            let format_line = |cells: &[&str]| {
+                cells
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                    .collect::<Vec<String>>()
+                    .join(" | ")
+                    .trim_end()
+                    .to_string()
+            };
+
+            let mut output = format_line(&header);
+            output.push('\n');
+            output.push_str(
+                &widths
+                    .iter()
+                    .map(|width| "-".repeat(*width))
+                    .collect::<Vec<String>>()
+                    .join("-+-"),
+            );
+            output.push('\n');
+            for line in &lines {
+                let cells: Vec<&str> = line.iter().map(|cell| cell.as_str()).collect();
+                output.push_str(&format_line(&cells));
+                output.push('\n');
+            }
+            output
         }
     }
+}
+
+pub fn answers(input: &Input) -> Vec<(u8, String)> {
+    let cards =
+        parse_cards(input.lines()).unwrap_or_else(|(line, err)| panic!("line {}: {}", line, err));
+    let points = run_part_1(&cards).unwrap_or_else(|err| panic!("{}", err));
+    let total =
+        run_part_2(&cards, PastEndPolicy::default()).unwrap_or_else(|err| panic!("{}", err));
 
-    let points = cards.iter().map(|c| c.points()).sum::<usize>();
+    vec![(1, points.to_string()), (2, total.to_string())]
+}
+
+pub fn run(
+    input: &Input,
+    past_end: PastEndPolicy,
+    explain_format: Option<ExplainFormat>,
+    output: Option<PathBuf>,
+) {
+    info!("--- DAY 4 ----");
+
+    let cards = match parse_cards(input.lines()) {
+        Ok(cards) => cards,
+        Err((line, err)) => panic!("Invalid card at {}:{}: {}", input.name(), line, err),
+    };
+
+    let points = match run_part_1(&cards) {
+        Ok(points) => points,
+        Err(err) => panic!("Unable to count the points: {}", err),
+    };
     info!("Part 1: {}", points);
 
-    run_part_2(cards);
+    let total = match run_part_2(&cards, past_end) {
+        Ok(total) => total,
+        Err(err) => panic!("Unable to count the cards: {}", err),
+    };
+    info!("Part 2: {}", total);
+
+    if let Some(format) = explain_format {
+        let report = explain(&cascade(&cards, past_end).unwrap(), format);
+
+        match output {
+            Some(path) => {
+                fs::write(&path, report).expect("Couldn't write report");
+                info!("Report written to {}", path.display());
+            }
+            None => print!("{}", report),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -134,7 +544,7 @@ mod tests {
     #[test]
     fn test_parsing_card() {
         let text = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
-        let card = parse_card(text);
+        let card = parse_card(text).unwrap();
 
         assert_eq!(
             card,
@@ -142,11 +552,12 @@ mod tests {
                 id: 1,
                 winnings: vec![41, 48, 83, 86, 17],
                 numbers: vec![83, 86, 6, 31, 17, 9, 48, 53],
+                matches: 4,
             }
         );
 
         let text = "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19";
-        let card = parse_card(text);
+        let card = parse_card(text).unwrap();
 
         assert_eq!(
             card,
@@ -154,6 +565,7 @@ mod tests {
                 id: 2,
                 winnings: vec![13, 32, 20, 16, 61],
                 numbers: vec![61, 30, 68, 82, 17, 32, 24, 19],
+                matches: 2,
             }
         )
     }
@@ -161,26 +573,314 @@ mod tests {
     #[test]
     fn test_card_points() {
         let text = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
-        let card = parse_card(text);
-        assert_eq!(card.points(), 8);
+        let card = parse_card(text).unwrap();
+        assert_eq!(card.points(), Ok(8));
 
         let text = "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19";
-        let card = parse_card(text);
-        assert_eq!(card.points(), 2);
+        let card = parse_card(text).unwrap();
+        assert_eq!(card.points(), Ok(2));
 
         let text = "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36";
-        let card = parse_card(text);
-        assert_eq!(card.points(), 0);
+        let card = parse_card(text).unwrap();
+        assert_eq!(card.points(), Ok(0));
+    }
+
+    #[test]
+    fn test_points_overflow() {
+        // Repeated numbers all match, 64 of them are worth 2^63 points
+        let card = |id: usize, matches: usize| {
+            parse_card(&format!(
+                "Card {}: 1 | {}",
+                id,
+                "1 ".repeat(matches).trim_end()
+            ))
+            .unwrap()
+        };
+        assert_eq!(card(1, 64).points(), Ok(1 << 63));
+        assert_eq!(
+            card(1, 70).points(),
+            Err(CardError::PointsOverflow { card: 1 })
+        );
+
+        assert_eq!(run_part_1(&[card(1, 64), card(2, 63)]), Ok(3 << 62));
+        assert_eq!(
+            run_part_1(&[card(1, 64), card(2, 64)]),
+            Err(CardError::PointsOverflow { card: 2 })
+        );
+        assert_eq!(
+            run_part_1(&[card(1, 70)]).unwrap_err().to_string(),
+            "Points of card 1 overflow"
+        );
     }
 
     #[test]
     fn test_winning_copies() {
         let text = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
-        let card = parse_card(text);
-        assert_eq!(card.winning_copies(), vec![2, 3, 4, 5]);
+        let card = parse_card(text).unwrap();
+        assert_eq!(
+            card.winning_copies(6, PastEndPolicy::Clamp),
+            Ok(vec![2, 3, 4, 5])
+        );
 
         let text = "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19";
-        let card = parse_card(text);
-        assert_eq!(card.winning_copies(), vec![3, 4]);
+        let card = parse_card(text).unwrap();
+        assert_eq!(card.winning_copies(6, PastEndPolicy::Clamp), Ok(vec![3, 4]));
+    }
+
+    #[test]
+    fn test_winning_set() {
+        let set = WinningSet::new(&[0, 41, 99]);
+        assert!(matches!(set, WinningSet::Bits(_)));
+        assert!(set.contains(0) && set.contains(41) && set.contains(99));
+        assert!(!set.contains(1) && !set.contains(100) && !set.contains(141));
+
+        let set = WinningSet::new(&[41, 100, 1234]);
+        assert!(matches!(set, WinningSet::Hashed(_)));
+        assert!(set.contains(41) && set.contains(100) && set.contains(1234));
+        assert!(!set.contains(99));
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        let card = parse_card("Card 7: 100 5 250 | 250 6 100 100 5").unwrap();
+        assert_eq!(card.matches, 4);
+        assert_eq!(card.points(), Ok(8));
+        assert_eq!(
+            card.winning_copies(20, PastEndPolicy::Error),
+            Ok(vec![8, 9, 10, 11])
+        );
+    }
+
+    fn example_cards() -> Vec<Card> {
+        [
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+        ]
+        .iter()
+        .map(|text| parse_card(text).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_part_2() {
+        assert_eq!(run_part_2(&example_cards(), PastEndPolicy::Error), Ok(30));
+    }
+
+    #[test]
+    fn test_part_2_huge_copy_counts() {
+        // Every card wins a copy of the next two cards, so copy counts grow
+        // like the Fibonacci sequence
+        let cards: Vec<Card> = (1..=100)
+            .map(|id| Card::new(id, vec![1, 2], vec![1, 2]))
+            .collect();
+
+        let mut expected: Vec<u128> = vec![];
+        for i in 0..100_usize {
+            let received: u128 = expected[i.saturating_sub(2)..i].iter().sum();
+            expected.push(1 + received);
+        }
+
+        let total = run_part_2(&cards, PastEndPolicy::Clamp).unwrap();
+        assert_eq!(total, expected.iter().sum::<u128>());
+        assert!(total > u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_part_2_copies_overflow() {
+        // The running total of the Fibonacci copy counts passes u128::MAX at
+        // the 183rd card
+        let cards: Vec<Card> = (1..=200)
+            .map(|id| Card::new(id, vec![1, 2], vec![1, 2]))
+            .collect();
+
+        let err = run_part_2(&cards, PastEndPolicy::Clamp).unwrap_err();
+        assert_eq!(err, CardError::CopiesOverflow { card: 183 });
+        assert_eq!(err.to_string(), "Copy count of card 183 overflows");
+        assert_eq!(cascade(&cards, PastEndPolicy::Clamp).unwrap_err(), err);
+    }
+
+    #[test]
+    fn test_cascade() {
+        let cards = example_cards();
+        let rows = cascade(&cards, PastEndPolicy::Error).unwrap();
+
+        assert_eq!(
+            rows[3],
+            CascadeRow {
+                id: 4,
+                matches: 1,
+                received: vec![(1, 1), (2, 2), (3, 4)],
+                copies: 8,
+                running_total: 1 + 2 + 4 + 8,
+            }
+        );
+        assert_eq!(
+            Ok(rows.last().unwrap().running_total),
+            run_part_2(&cards, PastEndPolicy::Error)
+        );
+
+        let copies: Vec<u128> = rows.iter().map(|row| row.copies).collect();
+        assert_eq!(copies, vec![1, 2, 4, 8, 14, 1]);
+    }
+
+    #[test]
+    fn test_explain() {
+        let rows = cascade(&example_cards(), PastEndPolicy::Error).unwrap();
+
+        let csv = explain(&rows, ExplainFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "card,matches,received_from,copies,running_total");
+        assert_eq!(lines[1], "1,4,,1,1");
+        assert_eq!(lines[5], "5,0,1:1;3:4;4:8,14,29");
+
+        let table = explain(&rows, ExplainFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "Card | Matches | Received from | Copies | Total");
+        assert_eq!(lines[1], "-----+---------+---------------+--------+------");
+        assert_eq!(lines[5], "4    | 1       | 1:1 2:2 3:4   | 8      | 15");
+    }
+
+    #[test]
+    fn test_parsing_errors() {
+        assert_eq!(
+            parse_card("Crad 1: 41 48 | 83 86"),
+            Err(CardError::MissingPrefix)
+        );
+        assert_eq!(
+            parse_card("Card 1 41 48 | 83 86"),
+            Err(CardError::MissingSeparator(':'))
+        );
+        assert_eq!(
+            parse_card("Card 1: 41 48 83 86"),
+            Err(CardError::MissingSeparator('|'))
+        );
+        assert_eq!(
+            parse_card("Card one: 41 48 | 83 86"),
+            Err(CardError::InvalidId {
+                token: "one".to_string(),
+                column: 6
+            })
+        );
+        assert_eq!(
+            parse_card("Card one: 41 48 | 83 86")
+                .unwrap_err()
+                .to_string(),
+            "Invalid card id \"one\" at column 6"
+        );
+        assert_eq!(
+            parse_card("Card   1 2: 41 48 | 83 86"),
+            Err(CardError::InvalidId {
+                token: "1 2".to_string(),
+                column: 8
+            })
+        );
+        assert_eq!(
+            parse_card("Card1: 41 48 | 83 86"),
+            Err(CardError::InvalidId {
+                token: "1".to_string(),
+                column: 5
+            })
+        );
+        assert_eq!(
+            parse_card("Card 1: 41 4x8 | 83 86"),
+            Err(CardError::InvalidNumber {
+                token: "4x8".to_string(),
+                column: 12
+            })
+        );
+        assert_eq!(
+            parse_card("Card  12: 41 48 |  83 +86"),
+            Err(CardError::InvalidNumber {
+                token: "+86".to_string(),
+                column: 23
+            })
+        );
+        assert_eq!(
+            parse_card("Card 1: 41 48 41 | 83 86"),
+            Err(CardError::DuplicateWinning {
+                number: 41,
+                column: 15
+            })
+        );
+
+        let err = parse_card("Card 1: 41 4x8 | 83 86").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid number \"4x8\" at column 12");
+    }
+
+    #[test]
+    fn test_parsing_cards_sequence() {
+        let cards = parse_cards(["Card 1: 41 | 41", "Card 2: 13 | 61"]).unwrap();
+        assert_eq!(cards.len(), 2);
+
+        assert_eq!(
+            parse_cards(["Card 1: 41 | 41", "Card 3: 13 | 61"]),
+            Err((
+                2,
+                CardError::UnexpectedId {
+                    expected: 2,
+                    found: 3
+                }
+            ))
+        );
+        assert_eq!(
+            parse_cards(["Card 1: 41 | 41", "Card 2: 13 | 61", "Card 3: 13 | x"]),
+            Err((
+                3,
+                CardError::InvalidNumber {
+                    token: "x".to_string(),
+                    column: 14
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_copies_past_end() {
+        let card = parse_card("Card 5: 1 2 3 | 1 2 3").unwrap();
+        assert_eq!(
+            card.winning_copies(8, PastEndPolicy::Error),
+            Ok(vec![6, 7, 8])
+        );
+        assert_eq!(card.winning_copies(6, PastEndPolicy::Clamp), Ok(vec![6]));
+        assert_eq!(
+            card.winning_copies(6, PastEndPolicy::Error),
+            Err(CardError::CopyPastEnd {
+                card: 5,
+                copy: 8,
+                table_size: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_part_2_final_cards() {
+        // The last card wins, but there is nothing left to copy
+        let cards = parse_cards(["Card 1: 5 | 6", "Card 2: 5 | 5"]).unwrap();
+        assert_eq!(run_part_2(&cards, PastEndPolicy::Clamp), Ok(2));
+        assert_eq!(
+            run_part_2(&cards, PastEndPolicy::Error),
+            Err(CardError::CopyPastEnd {
+                card: 2,
+                copy: 3,
+                table_size: 2
+            })
+        );
+
+        // The one before last wins more copies than there are cards left
+        let cards = parse_cards(["Card 1: 5 6 | 5 6", "Card 2: 5 | 6"]).unwrap();
+        assert_eq!(run_part_2(&cards, PastEndPolicy::Clamp), Ok(3));
+        assert!(run_part_2(&cards, PastEndPolicy::Error).is_err());
+
+        let rows = cascade(&cards, PastEndPolicy::Clamp).unwrap();
+        assert_eq!(rows[1].received, vec![(1, 1)]);
+        assert_eq!(rows[1].running_total, 3);
+
+        // Winning exactly up to the last card is fine
+        let cards = parse_cards(["Card 1: 5 | 5", "Card 2: 5 | 6"]).unwrap();
+        assert_eq!(run_part_2(&cards, PastEndPolicy::Error), Ok(3));
     }
 }
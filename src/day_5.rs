@@ -1,99 +1,1703 @@
 use human_time::ToHumanTimeString;
-use log::info;
+use log::{debug, info, warn};
 use rayon::prelude::*;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{HashMap, VecDeque},
+    fmt, fs,
+    ops::Range,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
+use tracing::{field::Empty, instrument, Span};
+
+use crate::{input::Input, render::escape_xml};
 
 #[derive(Debug, Clone)]
 struct MapLine {
-    destination: u32,
-    source: u32,
-    source_max: u32,
+    destination: u64,
+    source: u64,
+    source_max: u64,
 }
 
 impl MapLine {
-    fn is_in_range(&self, value: u32) -> bool {
+    fn is_in_range(&self, value: u64) -> bool {
         value >= self.source && value < self.source_max
     }
+
+    fn map(&self, value: u64) -> u64 {
+        value - self.source + self.destination
+    }
+
+    fn is_in_destination_range(&self, value: u64) -> bool {
+        value >= self.destination && value - self.destination < self.source_max - self.source
+    }
+
+    fn unmap(&self, value: u64) -> u64 {
+        value - self.destination + self.source
+    }
+
+    fn length(&self) -> u64 {
+        self.source_max - self.source
+    }
+
+    fn overlaps_source(&self, other: &MapLine) -> bool {
+        self.source < other.source_max && other.source < self.source_max
+    }
+
+    fn overlaps_destination(&self, other: &MapLine) -> bool {
+        self.destination < other.destination + other.length()
+            && other.destination < self.destination + self.length()
+    }
+}
+
+/// Conversion from one category (e.g. `seed`) to another (e.g. `soil`)
+#[derive(Debug, Clone)]
+struct CategoryMap {
+    source: String,
+    destination: String,
+    lines: Vec<MapLine>,
+}
+
+/// Seeds to plant and the maps converting them from category to category
+#[derive(Debug, Clone)]
+struct Almanac {
+    seeds: Vec<u64>,
+    maps: Vec<CategoryMap>,
+    /// Suspicious lines that don't change how values are converted
+    warnings: Vec<AlmanacWarning>,
+}
+
+impl Almanac {
+    /// Maps to go through, in order, to convert `from` values into `to` values
+    fn path(&self, from: &str, to: &str) -> Result<Vec<&CategoryMap>, AlmanacError> {
+        for category in [from, to] {
+            if !self
+                .maps
+                .iter()
+                .any(|map| map.source == category || map.destination == category)
+            {
+                return Err(AlmanacError::UnknownCategory(category.to_string()));
+            }
+        }
+
+        // Breadth-first search, remembering the map used to reach each category
+        let mut reached_by: HashMap<&str, Option<&CategoryMap>> = HashMap::from([(from, None)]);
+        let mut queue: VecDeque<&str> = VecDeque::from([from]);
+
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                break;
+            }
+
+            for map in self.maps.iter().filter(|map| map.source == category) {
+                if !reached_by.contains_key(map.destination.as_str()) {
+                    reached_by.insert(&map.destination, Some(map));
+                    queue.push_back(&map.destination);
+                }
+            }
+        }
+
+        let mut path: Vec<&CategoryMap> = vec![];
+        let mut category = to;
+        while let Some(step) = reached_by.get(category) {
+            match step {
+                Some(map) => {
+                    path.push(map);
+                    category = &map.source;
+                }
+                None => {
+                    path.reverse();
+                    return Ok(path);
+                }
+            }
+        }
+
+        Err(AlmanacError::NoPath {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum AlmanacError {
+    InvalidNumber {
+        line: usize,
+        token: String,
+    },
+    InvalidMapLine {
+        line: usize,
+    },
+    /// Map header not following the `source-to-destination map:` format
+    InvalidHeader {
+        line: usize,
+    },
+    /// `start + length` doesn't fit in a `u64`
+    RangeOverflow {
+        line: usize,
+        start: u64,
+        length: u64,
+    },
+    /// Values converted by both lines would depend on the order of the file
+    OverlappingSources {
+        line: usize,
+        other_line: usize,
+    },
+    /// No `seeds:` line, or one without any seed
+    MissingSeeds,
+    /// Part 2 reads seeds by pairs, this one has no length
+    UnpairedSeed(u64),
+    SeedRangeOverflow {
+        start: u64,
+        length: u64,
+    },
+    UnknownCategory(String),
+    NoPath {
+        from: String,
+        to: String,
+    },
+}
+
+impl fmt::Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlmanacError::InvalidNumber { line, token } => {
+                write!(f, "Invalid number {:?} on line {}", token, line)
+            }
+            AlmanacError::InvalidMapLine { line } => {
+                write!(f, "Expected `destination source length` on line {}", line)
+            }
+            AlmanacError::RangeOverflow {
+                line,
+                start,
+                length,
+            } => write!(
+                f,
+                "Range of {} values from {} on line {} exceeds {}",
+                length,
+                start,
+                line,
+                u64::MAX
+            ),
+            AlmanacError::InvalidHeader { line } => {
+                write!(f, "Expected `source-to-destination map:` on line {}", line)
+            }
+            AlmanacError::UnknownCategory(category) => {
+                write!(f, "No map converts from or to {:?}", category)
+            }
+            AlmanacError::NoPath { from, to } => {
+                write!(f, "No chain of maps converts {:?} into {:?}", from, to)
+            }
+            AlmanacError::OverlappingSources { line, other_line } => write!(
+                f,
+                "Source range on line {} overlaps the one on line {}",
+                line, other_line
+            ),
+            AlmanacError::MissingSeeds => write!(f, "No seeds listed on a `seeds:` line"),
+            AlmanacError::UnpairedSeed(seed) => {
+                write!(f, "Seed range starting at {} has no length", seed)
+            }
+            AlmanacError::SeedRangeOverflow { start, length } => write!(
+                f,
+                "Seed range of {} values from {} exceeds {}",
+                length,
+                start,
+                u64::MAX
+            ),
+        }
+    }
 }
 
-fn find_source_to_destination(value: u32, maps: &Vec<MapLine>) -> u32 {
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum AlmanacWarning {
+    /// Line converting no value at all
+    ZeroLength { line: usize },
+    /// Two lines converting different values into the same ones
+    DestinationCollision { line: usize, other_line: usize },
+}
+
+impl fmt::Display for AlmanacWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlmanacWarning::ZeroLength { line } => write!(f, "Empty range on line {}", line),
+            AlmanacWarning::DestinationCollision { line, other_line } => write!(
+                f,
+                "Destination range on line {} overlaps the one on line {}",
+                line, other_line
+            ),
+        }
+    }
+}
+
+/// Check a new line against the lines already read for the same map
+fn validate_map_line(
+    map_line: &MapLine,
+    line: usize,
+    previous: &[(usize, MapLine)],
+) -> Result<Vec<AlmanacWarning>, AlmanacError> {
+    if map_line.length() == 0 {
+        return Ok(vec![AlmanacWarning::ZeroLength { line }]);
+    }
+
+    let mut warnings: Vec<AlmanacWarning> = vec![];
+
+    for (other_line, other) in previous {
+        if other.length() == 0 {
+            continue;
+        }
+
+        if map_line.overlaps_source(other) {
+            return Err(AlmanacError::OverlappingSources {
+                line,
+                other_line: *other_line,
+            });
+        }
+
+        if map_line.overlaps_destination(other) {
+            warnings.push(AlmanacWarning::DestinationCollision {
+                line,
+                other_line: *other_line,
+            });
+        }
+    }
+
+    Ok(warnings)
+}
+
+fn find_source_to_destination(value: u64, maps: &[MapLine]) -> u64 {
     maps.iter()
         .find_map(|map_line| {
             if map_line.is_in_range(value) {
-                return Some(value - map_line.source + map_line.destination);
+                return Some(map_line.map(value));
             }
             None
         })
         .unwrap_or(value)
 }
 
-fn parse(reader: BufReader<File>) -> (Vec<u32>, Vec<Vec<MapLine>>) {
-    let mut seeds: Vec<u32> = vec![];
+/// Every value converted into `value` by the map: the conversion isn't
+/// one-to-one, as values not covered by any line keep their number.
+fn find_destination_to_sources(value: u64, maps: &[MapLine]) -> Vec<u64> {
+    let mut sources: Vec<u64> = maps
+        .iter()
+        .filter(|map_line| map_line.is_in_destination_range(value))
+        .map(|map_line| map_line.unmap(value))
+        .chain([value])
+        // Only keep the sources actually converted by the line they come from
+        .filter(|source| find_source_to_destination(*source, maps) == value)
+        .collect();
+
+    sources.sort();
+    sources.dedup();
+    sources
+}
+
+/// Every seed ending up at the given location, going back through the maps
+fn seeds_for_location(location: u64, maps: &[&CategoryMap]) -> Vec<u64> {
+    maps.iter().rev().fold(vec![location], |values, map| {
+        let mut sources: Vec<u64> = values
+            .into_iter()
+            .flat_map(|value| find_destination_to_sources(value, &map.lines))
+            .collect();
+
+        sources.sort();
+        sources.dedup();
+        sources
+    })
+}
+
+/// Location of a single seed, following every map
+fn location(seed: u64, maps: &[&CategoryMap]) -> u64 {
+    maps.iter().fold(seed, |value, map| {
+        find_source_to_destination(value, &map.lines)
+    })
+}
+
+/// Several maps composed into a single one: sorted, non-overlapping lines
+/// covering every value from 0 to `u64::MAX` (excluded)
+#[derive(Debug, Clone)]
+struct ComposedMap {
+    lines: Vec<MapLine>,
+}
+
+impl ComposedMap {
+    fn identity() -> Self {
+        ComposedMap {
+            lines: vec![MapLine {
+                destination: 0,
+                source: 0,
+                source_max: u64::MAX,
+            }],
+        }
+    }
+
+    /// Single layer of lines, resolving overlaps the way
+    /// `find_source_to_destination` does: the first line wins
+    fn from_lines(lines: &[MapLine]) -> Self {
+        let mut bounds: Vec<u64> = lines
+            .iter()
+            .flat_map(|line| [line.source, line.source_max])
+            .chain([0, u64::MAX])
+            .collect();
+        bounds.sort();
+        bounds.dedup();
+
+        let pieces = bounds
+            .windows(2)
+            .map(|bound| MapLine {
+                destination: find_source_to_destination(bound[0], lines),
+                source: bound[0],
+                source_max: bound[1],
+            })
+            .collect();
+
+        ComposedMap::merged(pieces)
+    }
+
+    #[instrument(name = "compose", skip_all, fields(maps = maps.len(), lines = Empty))]
+    fn new(maps: &[&CategoryMap]) -> Self {
+        let composed = maps.iter().fold(ComposedMap::identity(), |composed, map| {
+            composed.then(&ComposedMap::from_lines(&map.lines))
+        });
+
+        Span::current().record("lines", composed.lines.len());
+        composed
+    }
+
+    /// Apply `self` then `next`, splitting lines on the boundaries of `next`
+    fn then(&self, next: &ComposedMap) -> Self {
+        let mut pieces: Vec<MapLine> = vec![];
+
+        for line in &self.lines {
+            let mut source = line.source;
+
+            while source < line.source_max {
+                let destination = line.map(source);
+                let next_line = next.line_for(destination);
+
+                // Stop at the end of either line, whichever comes first
+                let length = (line.source_max - source)
+                    .min(next_line.map_or(u64::MAX - destination, |l| l.source_max - destination));
+
+                pieces.push(MapLine {
+                    destination: next_line.map_or(destination, |l| l.map(destination)),
+                    source,
+                    source_max: source + length,
+                });
+                source += length;
+            }
+        }
+
+        ComposedMap::merged(pieces)
+    }
+
+    /// Join consecutive lines applying the same shift
+    fn merged(pieces: Vec<MapLine>) -> Self {
+        let mut lines: Vec<MapLine> = vec![];
+
+        for piece in pieces {
+            match lines.last_mut() {
+                Some(last)
+                    if last.source_max == piece.source
+                        && last.map(last.source_max - 1).checked_add(1)
+                            == Some(piece.destination) =>
+                {
+                    last.source_max = piece.source_max;
+                }
+                _ => lines.push(piece),
+            }
+        }
+
+        ComposedMap { lines }
+    }
+
+    fn line_for(&self, value: u64) -> Option<&MapLine> {
+        let index = self.lines.partition_point(|line| line.source_max <= value);
+        self.lines.get(index).filter(|line| line.is_in_range(value))
+    }
+
+    fn get(&self, value: u64) -> u64 {
+        self.line_for(value).map_or(value, |line| line.map(value))
+    }
+
+    fn to_table(&self) -> String {
+        let mut output = format!(
+            "{:>20} {:>20} {:>20} {:>21}\n",
+            "source", "source end", "destination", "shift"
+        );
+
+        for line in &self.lines {
+            output.push_str(&format!(
+                "{:>20} {:>20} {:>20} {:>+21}\n",
+                line.source,
+                line.source_max,
+                line.destination,
+                line.destination as i128 - line.source as i128
+            ));
+        }
+
+        output
+    }
+}
+
+/// Part 1: every number of `seeds:` is a seed on its own
+#[instrument(name = "part_1", skip_all, fields(seeds = seeds.len()))]
+fn lowest_seed_location(seeds: &[u64], composed: &ComposedMap) -> Option<u64> {
+    seeds.iter().map(|seed| composed.get(*seed)).min()
+}
+
+/// Map whole ranges of values through a layer, splitting them wherever they
+/// cross the boundaries of a `MapLine`
+fn map_ranges(ranges: Vec<Range<u64>>, maps: &[MapLine]) -> Vec<Range<u64>> {
+    let mut mapped: Vec<Range<u64>> = vec![];
+    let mut pending = ranges;
 
-    let mut maps: Vec<Vec<MapLine>> = Vec::with_capacity(7);
+    for map_line in maps {
+        let mut unmapped: Vec<Range<u64>> = vec![];
 
-    for line in reader.lines() {
-        let line = line.expect("Unable to read line");
+        for range in pending {
+            let start = range.start.max(map_line.source);
+            let end = range.end.min(map_line.source_max);
 
-        if line.starts_with("seeds:") {
-            seeds = line
-                .replace("seeds: ", "")
-                .split(" ")
-                .filter_map(|s| s.parse().ok())
-                .collect();
+            if start >= end {
+                unmapped.push(range);
+                continue;
+            }
+
+            mapped.push(map_line.map(start)..map_line.map(end - 1) + 1);
+
+            if range.start < start {
+                unmapped.push(range.start..start);
+            }
+            if end < range.end {
+                unmapped.push(end..range.end);
+            }
         }
 
-        if line.contains("map:") {
-            maps.push(vec![]);
-        } else if line == "" {
+        pending = unmapped;
+    }
+
+    // Values not covered by any line keep their number
+    mapped.append(&mut pending);
+    mapped
+}
+
+/// Seed ranges given as `start length` pairs
+fn seed_ranges(seeds: &[u64]) -> Result<Vec<Range<u64>>, AlmanacError> {
+    seeds
+        .chunks(2)
+        .map(|r| match *r {
+            [start, length] => start
+                .checked_add(length)
+                .map(|end| start..end)
+                .ok_or(AlmanacError::SeedRangeOverflow { start, length }),
+            _ => Err(AlmanacError::UnpairedSeed(r[0])),
+        })
+        .collect()
+}
+
+/// Lowest location reachable from the given seed ranges, following the ranges
+/// layer by layer instead of every single seed
+#[instrument(name = "part_2", skip_all, fields(seed_ranges = seeds.len(), location_ranges = Empty))]
+fn lowest_location(seeds: Vec<Range<u64>>, maps: &[&CategoryMap]) -> Option<u64> {
+    let locations = maps.iter().fold(seeds, |ranges, map| {
+        let mapped = map_ranges(ranges, &map.lines);
+        debug!("{}: {} ranges", map.destination, mapped.len());
+        mapped
+    });
+
+    Span::current().record("location_ranges", locations.len());
+    locations.iter().map(|range| range.start).min()
+}
+
+/// Number of seeds each brute force task maps before reporting back
+const BRUTE_FORCE_CHUNK: u64 = 1 << 20;
+
+/// Lowest location found by mapping every seed one by one.
+///
+/// Progress is logged every `progress_interval` while the seeds are mapped in
+/// parallel, on as many threads as rayon's pool has. Setting `cancelled` stops
+/// the search, which then returns the lowest location found so far.
+fn brute_force_lowest_location(
+    seeds: Vec<Range<u64>>,
+    maps: &[&CategoryMap],
+    progress_interval: Duration,
+    cancelled: &AtomicBool,
+) -> Option<u64> {
+    let total: u64 = seeds
+        .iter()
+        .map(|range| range.end - range.start)
+        .fold(0, u64::saturating_add);
+    let chunks: Vec<Range<u64>> = seeds
+        .into_iter()
+        .flat_map(|range| {
+            range
+                .clone()
+                .step_by(BRUTE_FORCE_CHUNK as usize)
+                .map(move |start| start..start.saturating_add(BRUTE_FORCE_CHUNK).min(range.end))
+        })
+        .collect();
+
+    let processed = AtomicU64::new(0);
+    let lowest = AtomicU64::new(u64::MAX);
+    let done = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let start = Instant::now();
+            let mut last_report = start;
+
+            while !done.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(50).min(progress_interval));
+                if last_report.elapsed() < progress_interval {
+                    continue;
+                }
+                last_report = Instant::now();
+
+                let count = processed.load(Ordering::Relaxed);
+                let rate = count as f64 / start.elapsed().as_secs_f64();
+                let eta =
+                    Duration::from_secs_f64(total.saturating_sub(count) as f64 / rate.max(1.0));
+                info!(
+                    "{}/{} seeds ({:.1}%), {:.0} seeds/s, ETA {}, lowest so far: {}",
+                    count,
+                    total,
+                    count as f64 * 100.0 / total as f64,
+                    rate,
+                    eta.to_human_time_string(),
+                    lowest.load(Ordering::Relaxed)
+                );
+            }
+        });
+
+        let map_chunk = |chunk: Range<u64>| {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let size = chunk.end - chunk.start;
+            if let Some(chunk_lowest) = chunk.map(|seed| location(seed, maps)).min() {
+                lowest.fetch_min(chunk_lowest, Ordering::Relaxed);
+            }
+            processed.fetch_add(size, Ordering::Relaxed);
+        };
+
+        // Keep a plain loop for a single thread, to compare against rayon
+        if rayon::current_num_threads() == 1 {
+            chunks.into_iter().for_each(map_chunk);
+        } else {
+            chunks.into_par_iter().for_each(map_chunk);
+        }
+
+        done.store(true, Ordering::Relaxed);
+    });
+
+    match lowest.into_inner() {
+        u64::MAX if processed.into_inner() == 0 => None,
+        lowest => Some(lowest),
+    }
+}
+
+/// Lowest location found by scanning locations upward until one of them
+/// comes from a seed of the given ranges
+fn reverse_search_lowest_location(seeds: &[Range<u64>], maps: &[&CategoryMap]) -> Option<u64> {
+    if seeds.iter().all(|range| range.is_empty()) {
+        return None;
+    }
+
+    (0..=u64::MAX).find(|location| {
+        seeds_for_location(*location, maps)
+            .iter()
+            .any(|seed| seeds.iter().any(|range| range.contains(seed)))
+    })
+}
+
+/// Output format of the range visualization
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum RangesFormat {
+    Svg,
+    Text,
+}
+
+impl FromStr for RangesFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(RangesFormat::Svg),
+            "text" => Ok(RangesFormat::Text),
+            _ => Err(format!("Unknown ranges format: {}", s)),
+        }
+    }
+}
+
+/// Ranges reached in every category of the path, starting with the seeds.
+/// Each range comes with the index of the seed range it originates from.
+fn range_stages(seeds: &[Range<u64>], maps: &[&CategoryMap]) -> Vec<Vec<(usize, Range<u64>)>> {
+    let mut stages: Vec<Vec<(usize, Range<u64>)>> =
+        vec![seeds.iter().cloned().enumerate().collect()];
+
+    for map in maps {
+        let mut next: Vec<(usize, Range<u64>)> = stages
+            .last()
+            .unwrap()
+            .iter()
+            .flat_map(|(origin, range)| {
+                map_ranges(vec![range.clone()], &map.lines)
+                    .into_iter()
+                    .map(|mapped| (*origin, mapped))
+            })
+            .collect();
+        next.sort_by_key(|(origin, range)| (range.start, *origin));
+        stages.push(next);
+    }
+
+    stages
+}
+
+fn format_range(range: &Range<u64>) -> String {
+    format!("{}..{}", range.start, range.end)
+}
+
+fn ranges_text(seeds: &[Range<u64>], maps: &[&CategoryMap], first_category: &str) -> String {
+    let stages = range_stages(seeds, maps);
+    let categories = [first_category]
+        .into_iter()
+        .chain(maps.iter().map(|map| map.destination.as_str()));
+
+    let mut output = String::new();
+
+    for (i, (category, stage)) in categories.zip(&stages).enumerate() {
+        if let Some(map) = i.checked_sub(1).map(|index| maps[index]) {
+            output.push_str(&format!("{}-to-{}:\n", map.source, map.destination));
+            for line in &map.lines {
+                output.push_str(&format!(
+                    "  {} -> {} ({:+})\n",
+                    format_range(&(line.source..line.source_max)),
+                    format_range(&(line.destination..line.destination + line.length())),
+                    line.destination as i128 - line.source as i128
+                ));
+            }
+        }
+
+        output.push_str(&format!("{}: {} ranges\n", category, stage.len()));
+        for (origin, range) in stage {
+            output.push_str(&format!(
+                "  {} (seed range {})\n",
+                format_range(range),
+                origin
+            ));
+        }
+    }
+
+    output
+}
+
+fn ranges_svg(seeds: &[Range<u64>], maps: &[&CategoryMap], first_category: &str) -> String {
+    const COLUMN_WIDTH: f64 = 180.0;
+    const BAR_WIDTH: f64 = 16.0;
+    const MARGIN: f64 = 40.0;
+    const PLOT_HEIGHT: f64 = 600.0;
+    const COLORS: [&str; 8] = [
+        "#e53935", "#1e88e5", "#43a047", "#fb8c00", "#8e24aa", "#00acc1", "#6d4c41", "#3949ab",
+    ];
+
+    let stages = range_stages(seeds, maps);
+    let categories: Vec<String> = [first_category]
+        .into_iter()
+        .chain(maps.iter().map(|map| map.destination.as_str()))
+        .map(escape_xml)
+        .collect();
+
+    // Scale every axis to the highest value found in the ranges or the maps
+    let max_value = maps
+        .iter()
+        .flat_map(|map| &map.lines)
+        .flat_map(|line| [line.source_max, line.destination + line.length()])
+        .chain(stages.iter().flatten().map(|(_, range)| range.end))
+        .max()
+        .unwrap_or(1)
+        .max(1) as f64;
+    let y = |value: u64| MARGIN + value as f64 / max_value * PLOT_HEIGHT;
+    let x = |column: usize| MARGIN + column as f64 * COLUMN_WIDTH;
+
+    let width = x(categories.len() - 1) + BAR_WIDTH + MARGIN * 2.0;
+    let height = PLOT_HEIGHT + MARGIN * 2.0;
+
+    let mut output = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"12\">\n",
+        width, height
+    );
+    output.push_str(&format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>\n",
+        width, height
+    ));
+
+    // Map lines, from the source ranges on the left axis to the destination
+    // ranges on the right one
+    for (i, map) in maps.iter().enumerate() {
+        for line in &map.lines {
+            output.push_str(&format!(
+                "<polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"#9e9e9e\" fill-opacity=\"0.3\" stroke=\"#757575\" stroke-width=\"0.5\"><title>{}-to-{}: {} -&gt; {}</title></polygon>\n",
+                x(i) + BAR_WIDTH,
+                y(line.source),
+                x(i) + BAR_WIDTH,
+                y(line.source_max),
+                x(i + 1),
+                y(line.destination + line.length()),
+                x(i + 1),
+                y(line.destination),
+                escape_xml(&map.source),
+                escape_xml(&map.destination),
+                format_range(&(line.source..line.source_max)),
+                format_range(&(line.destination..line.destination + line.length()))
+            ));
+        }
+    }
+
+    for (i, (category, stage)) in categories.iter().zip(&stages).enumerate() {
+        output.push_str(&format!(
+            "<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\" stroke=\"#212121\"/>\n",
+            x(i) + BAR_WIDTH / 2.0,
+            y(0),
+            y(max_value as u64)
+        ));
+        output.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\">{} ({})</text>\n",
+            x(i),
+            MARGIN / 2.0,
+            category,
+            stage.len()
+        ));
+
+        // Fragments of the seed ranges, coloured after the range they come from
+        for (origin, range) in stage {
+            output.push_str(&format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{}: {} (seed range {})</title></rect>\n",
+                x(i),
+                y(range.start),
+                BAR_WIDTH,
+                (y(range.end) - y(range.start)).max(1.0),
+                COLORS[origin % COLORS.len()],
+                category,
+                format_range(range),
+                origin
+            ));
+        }
+    }
+
+    output.push_str("</svg>\n");
+    output
+}
+
+fn parse_numbers(text: &str, line: usize) -> Result<Vec<u64>, AlmanacError> {
+    text.split_whitespace()
+        .map(|token| {
+            token.parse().map_err(|_| AlmanacError::InvalidNumber {
+                line,
+                token: token.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[instrument(name = "parse", skip_all, fields(seeds = Empty, maps = Empty))]
+fn parse(text: &str) -> Result<Almanac, AlmanacError> {
+    let mut seeds: Vec<u64> = vec![];
+
+    let mut maps: Vec<CategoryMap> = Vec::with_capacity(7);
+    let mut warnings: Vec<AlmanacWarning> = vec![];
+    // Lines of the current map along with their line number
+    let mut map_lines: Vec<(usize, MapLine)> = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+
+        if let Some(values) = line.strip_prefix("seeds:") {
+            seeds = parse_numbers(values, line_number)?;
+        } else if let Some(header) = line.strip_suffix(" map:") {
+            let Some((source, destination)) = header.split_once("-to-") else {
+                return Err(AlmanacError::InvalidHeader { line: line_number });
+            };
+
+            maps.push(CategoryMap {
+                source: source.to_string(),
+                destination: destination.to_string(),
+                lines: vec![],
+            });
+            map_lines.clear();
+        } else if line.is_empty() {
             //
         } else {
-            let values: Vec<u32> = line.split(" ").filter_map(|s| s.parse().ok()).collect();
+            let values = parse_numbers(line, line_number)?;
+            let [destination, source, length] = values[..] else {
+                return Err(AlmanacError::InvalidMapLine { line: line_number });
+            };
+
+            let overflow = |start: u64| AlmanacError::RangeOverflow {
+                line: line_number,
+                start,
+                length,
+            };
+            destination
+                .checked_add(length)
+                .ok_or_else(|| overflow(destination))?;
             let map_line = MapLine {
-                destination: values[0],
-                source: values[1],
-                source_max: values[1] + values[2],
+                destination,
+                source,
+                source_max: source.checked_add(length).ok_or_else(|| overflow(source))?,
             };
 
+            warnings.append(&mut validate_map_line(&map_line, line_number, &map_lines)?);
+
             if let Some(mp) = maps.last_mut() {
-                mp.push(map_line);
+                map_lines.push((line_number, map_line.clone()));
+                mp.lines.push(map_line);
             }
         }
     }
 
-    (seeds, maps)
+    if seeds.is_empty() {
+        return Err(AlmanacError::MissingSeeds);
+    }
+
+    Span::current().record("seeds", seeds.len());
+    Span::current().record("maps", maps.len());
+    Ok(Almanac {
+        seeds,
+        maps,
+        warnings,
+    })
+}
+
+pub fn answers(input: &Input) -> Vec<(u8, String)> {
+    let almanac = parse(input.text()).unwrap_or_else(|err| panic!("{}", err));
+    let maps = almanac
+        .path("seed", "location")
+        .unwrap_or_else(|err| panic!("{}", err));
+    let seeds = seed_ranges(&almanac.seeds).unwrap_or_else(|err| panic!("{}", err));
+
+    let part_1 = lowest_seed_location(&almanac.seeds, &ComposedMap::new(&maps));
+    let part_2 = lowest_location(seeds, &maps);
+
+    vec![
+        (1, part_1.map_or(String::new(), |l| l.to_string())),
+        (2, part_2.map_or(String::new(), |l| l.to_string())),
+    ]
+}
+
+/// Location for the logs, `None` when no seed reaches one
+fn format_location(location: Option<u64>) -> String {
+    location.map_or_else(|| "None".to_string(), |location| location.to_string())
+}
+
+/// What `run` exports once both parts are solved
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Export {
+    /// Table of the maps composed into a single one
+    Composed,
+    /// Fragments of the seed ranges through every map
+    Ranges(RangesFormat),
+}
+
+/// Options of `run`, beyond the input
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Category the seeds are converted from
+    pub from: String,
+    /// Category the seeds are converted into
+    pub to: String,
+    /// Check the lowest location by mapping every seed one by one
+    pub brute_force: bool,
+    /// Check the lowest location by scanning locations upward
+    pub reverse_search: bool,
+    pub export: Option<Export>,
+    /// File the export is written to, stdout when `None`
+    pub output: Option<PathBuf>,
 }
 
-pub fn run() {
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            from: "seed".to_string(),
+            to: "location".to_string(),
+            brute_force: false,
+            reverse_search: false,
+            export: None,
+            output: None,
+        }
+    }
+}
+
+pub fn run(input: &Input, options: &Options) {
     info!("--- DAY 5 ----");
 
+    let from = options.from.as_str();
+    let to = options.to.as_str();
+
     let now = std::time::Instant::now();
 
-    let filename = "./src/inputs/day_5.txt";
-    let file = File::open(filename).expect("Couldn't read file");
-    let reader = BufReader::new(file);
-    let (init_seeds, maps) = parse(reader);
+    let almanac = match parse(input.text()) {
+        Ok(almanac) => almanac,
+        Err(err) => panic!("Invalid almanac {}: {}", input.name(), err),
+    };
 
-    let seeds = init_seeds
-        .into_par_iter()
-        .chunks(2)
-        .flat_map(|r| (r[0]..r[0] + r[1]).into_iter())
-        .map(|seed| {
-            let mut sed = seed.clone();
+    for warning in &almanac.warnings {
+        warn!("{}: {}", input.name(), warning);
+    }
 
-            for map in &maps {
-                let destination = find_source_to_destination(sed, &map);
-                sed = destination;
-            }
+    let maps = match almanac.path(from, to) {
+        Ok(maps) => maps,
+        Err(err) => panic!("Unable to convert {} to {}: {}", from, to, err),
+    };
+    info!(
+        "Converting {} through {} maps",
+        from,
+        maps.iter()
+            .map(|map| map.destination.as_str())
+            .collect::<Vec<&str>>()
+            .join(" -> ")
+    );
 
-            sed
-        })
-        .min();
+    let composed = ComposedMap::new(&maps);
+    debug!("Composed into {} lines", composed.lines.len());
 
-    info!("lowest: {:?}", seeds.unwrap());
+    info!(
+        "Part 1: {}",
+        format_location(lowest_seed_location(&almanac.seeds, &composed))
+    );
 
+    let seeds = match seed_ranges(&almanac.seeds) {
+        Ok(seeds) => seeds,
+        Err(err) => panic!("Invalid seed ranges: {}", err),
+    };
+    let lowest = lowest_location(seeds.clone(), &maps);
+    info!("Part 2: {}", format_location(lowest));
     info!("executed in {:?}", now.elapsed().to_human_time_string());
+
+    if let Some(location) = lowest {
+        let origins: Vec<u64> = seeds_for_location(location, &maps)
+            .into_iter()
+            .filter(|seed| seeds.iter().any(|range| range.contains(seed)))
+            .collect();
+
+        if origins.is_empty() {
+            warn!("No seed ends up at location {}", location);
+        } else {
+            info!("Location {} comes from {:?}", location, origins);
+        }
+    }
+
+    if options.reverse_search {
+        let now = std::time::Instant::now();
+
+        let reverse_lowest = reverse_search_lowest_location(&seeds, &maps);
+        info!("reverse search lowest: {}", format_location(reverse_lowest));
+
+        if reverse_lowest != lowest {
+            warn!(
+                "Reverse search found {:?} but range mapping found {:?}",
+                reverse_lowest, lowest
+            );
+        }
+
+        info!("executed in {:?}", now.elapsed().to_human_time_string());
+    }
+
+    if options.brute_force {
+        let now = std::time::Instant::now();
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let handler_cancelled = cancelled.clone();
+        ctrlc::set_handler(move || {
+            warn!("Interrupted, stopping the brute force");
+            handler_cancelled.store(true, Ordering::Relaxed);
+        })
+        .expect("Couldn't set the Ctrl-C handler");
+
+        let brute_force_lowest =
+            brute_force_lowest_location(seeds.clone(), &maps, Duration::from_secs(5), &cancelled);
+
+        if cancelled.load(Ordering::Relaxed) {
+            info!(
+                "brute force interrupted, lowest so far: {:?}",
+                brute_force_lowest
+            );
+        } else if brute_force_lowest != lowest {
+            warn!(
+                "Brute force found {:?} but range mapping found {:?}",
+                brute_force_lowest, lowest
+            );
+        } else {
+            info!(
+                "brute force lowest: {}",
+                format_location(brute_force_lowest)
+            );
+        }
+
+        info!("executed in {:?}", now.elapsed().to_human_time_string());
+    }
+
+    if let Some(export) = options.export {
+        let (name, rendering) = match export {
+            Export::Composed => ("Composed map", composed.to_table()),
+            Export::Ranges(RangesFormat::Svg) => ("Ranges", ranges_svg(&seeds, &maps, from)),
+            Export::Ranges(RangesFormat::Text) => ("Ranges", ranges_text(&seeds, &maps, from)),
+        };
+
+        match &options.output {
+            Some(path) => {
+                fs::write(path, rendering).expect("Couldn't write export");
+                info!("{} written to {}", name, path.display());
+            }
+            None => print!("{}", rendering),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALMANAC: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    #[test]
+    fn test_map_ranges() {
+        let maps = vec![
+            MapLine {
+                destination: 50,
+                source: 98,
+                source_max: 100,
+            },
+            MapLine {
+                destination: 52,
+                source: 50,
+                source_max: 98,
+            },
+        ];
+
+        // Split across both lines and the unmapped values below them
+        let mut ranges = map_ranges(
+            vec![Range {
+                start: 40,
+                end: 100,
+            }],
+            &maps,
+        );
+        ranges.sort_by_key(|r| r.start);
+        assert_eq!(ranges, vec![40..50, 50..52, 52..100]);
+
+        // Fully inside a single line
+        assert_eq!(
+            map_ranges(vec![Range { start: 79, end: 93 }], &maps),
+            vec![81..95]
+        );
+
+        // Outside of every line
+        assert_eq!(
+            map_ranges(vec![Range { start: 0, end: 10 }], &maps),
+            vec![0..10]
+        );
+    }
+
+    #[test]
+    fn test_lowest_seed_location() {
+        let almanac = parse(ALMANAC).unwrap();
+
+        let maps = almanac.path("seed", "location").unwrap();
+
+        let locations: Vec<u64> = almanac
+            .seeds
+            .iter()
+            .map(|seed| location(*seed, &maps))
+            .collect();
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+        assert_eq!(
+            lowest_seed_location(&almanac.seeds, &ComposedMap::new(&maps)),
+            Some(35)
+        );
+    }
+
+    #[test]
+    fn test_lowest_location() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+
+        assert_eq!(
+            lowest_location(seed_ranges(&almanac.seeds).unwrap(), &maps),
+            Some(46)
+        );
+        assert_eq!(
+            brute_force_lowest_location(
+                seed_ranges(&almanac.seeds).unwrap(),
+                &maps,
+                Duration::from_secs(60),
+                &AtomicBool::new(false)
+            ),
+            Some(46)
+        );
+    }
+
+    #[test]
+    fn test_values_past_u32() {
+        let text = "seeds: 4294967290 10 5 1
+
+seed-to-soil map:
+0 4294967295 10
+4294967300 0 10";
+        let almanac = parse(text).unwrap();
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+        assert_eq!(seeds, vec![4294967290..4294967300, 5..6]);
+
+        let maps = almanac.path("seed", "soil").unwrap();
+        assert_eq!(location(4294967296, &maps), 1);
+        assert_eq!(location(5, &maps), 4294967305);
+        assert_eq!(lowest_location(seeds.clone(), &maps), Some(0));
+        assert_eq!(
+            brute_force_lowest_location(
+                seeds,
+                &maps,
+                Duration::from_secs(60),
+                &AtomicBool::new(false)
+            ),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_overflowing_ranges() {
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+0 18446744073709551610 10";
+        assert_eq!(
+            parse(text).unwrap_err(),
+            AlmanacError::RangeOverflow {
+                line: 4,
+                start: 18446744073709551610,
+                length: 10
+            }
+        );
+
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+18446744073709551610 0 10";
+        assert_eq!(
+            parse(text).unwrap_err().to_string(),
+            "Range of 10 values from 18446744073709551610 on line 4 exceeds 18446744073709551615"
+        );
+
+        // Ending exactly at the end of the domain is fine
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+0 18446744073709551605 10";
+        assert!(parse(text).is_ok());
+
+        assert_eq!(
+            seed_ranges(&[18446744073709551610, 10]),
+            Err(AlmanacError::SeedRangeOverflow {
+                start: 18446744073709551610,
+                length: 10
+            })
+        );
+        assert_eq!(seed_ranges(&[1, 2, 3]), Err(AlmanacError::UnpairedSeed(3)));
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+0 1";
+        assert_eq!(
+            parse(text).unwrap_err(),
+            AlmanacError::InvalidMapLine { line: 4 }
+        );
+
+        let text = "seeds: 1 -2";
+        assert_eq!(
+            parse(text).unwrap_err().to_string(),
+            "Invalid number \"-2\" on line 1"
+        );
+    }
+
+    #[test]
+    fn test_missing_seeds() {
+        let maps = "seed-to-soil map:
+0 1 2";
+        assert_eq!(parse(maps).unwrap_err(), AlmanacError::MissingSeeds);
+
+        let text = format!("seeds:\n\n{}", maps);
+        assert_eq!(
+            parse(&text).unwrap_err().to_string(),
+            "No seeds listed on a `seeds:` line"
+        );
+    }
+
+    #[test]
+    fn test_category_names() {
+        let almanac = parse(ALMANAC).unwrap();
+
+        let names: Vec<(&str, &str)> = almanac
+            .maps
+            .iter()
+            .map(|map| (map.source.as_str(), map.destination.as_str()))
+            .collect();
+        assert_eq!(names[0], ("seed", "soil"));
+        assert_eq!(names[6], ("humidity", "location"));
+
+        let text = "seeds: 1 2
+
+seed-soil map:
+0 1 2";
+        assert_eq!(
+            parse(text).unwrap_err(),
+            AlmanacError::InvalidHeader { line: 3 }
+        );
+    }
+
+    #[test]
+    fn test_category_path() {
+        let almanac = parse(ALMANAC).unwrap();
+
+        let path = almanac.path("seed", "humidity").unwrap();
+        let destinations: Vec<&str> = path.iter().map(|map| map.destination.as_str()).collect();
+        assert_eq!(
+            destinations,
+            vec![
+                "soil",
+                "fertilizer",
+                "water",
+                "light",
+                "temperature",
+                "humidity"
+            ]
+        );
+
+        // Seed 79 is humidity 78 in the puzzle walkthrough
+        assert_eq!(location(79, &path), 78);
+
+        let path = almanac.path("water", "temperature").unwrap();
+        assert_eq!(path.len(), 2);
+        assert_eq!(location(81, &path), 78);
+
+        assert!(almanac.path("soil", "soil").unwrap().is_empty());
+
+        assert_eq!(
+            almanac.path("location", "seed").unwrap_err(),
+            AlmanacError::NoPath {
+                from: "location".to_string(),
+                to: "seed".to_string()
+            }
+        );
+        assert_eq!(
+            almanac.path("seed", "moon").unwrap_err(),
+            AlmanacError::UnknownCategory("moon".to_string())
+        );
+    }
+
+    #[test]
+    fn test_category_path_out_of_order() {
+        let text = "seeds: 1 2
+
+soil-to-water map:
+10 0 5
+
+seed-to-soil map:
+0 1 2";
+        let almanac = parse(text).unwrap();
+        let path = almanac.path("seed", "water").unwrap();
+
+        assert_eq!(path[0].source, "seed");
+        assert_eq!(path[1].source, "soil");
+        assert_eq!(location(1, &path), 10);
+
+        // A gap in the chain
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+0 1 2
+
+water-to-light map:
+10 0 5";
+        let almanac = parse(text).unwrap();
+        assert!(matches!(
+            almanac.path("seed", "light"),
+            Err(AlmanacError::NoPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_find_destination_to_sources() {
+        let almanac = parse(ALMANAC).unwrap();
+        let seed_to_soil = &almanac.maps[0].lines;
+
+        // Soil 81 only comes from seed 79
+        assert_eq!(find_destination_to_sources(81, seed_to_soil), vec![79]);
+        // Soil 50 comes from seed 98, seed 50 itself becomes soil 52
+        assert_eq!(find_destination_to_sources(50, seed_to_soil), vec![98]);
+        // Soil 10 is seed 10, nothing maps to it
+        assert_eq!(find_destination_to_sources(10, seed_to_soil), vec![10]);
+        // Seed 99 becomes soil 51, soil 99 comes from seed 97
+        assert_eq!(find_destination_to_sources(99, seed_to_soil), vec![97]);
+
+        for value in 0..120 {
+            for source in find_destination_to_sources(value, seed_to_soil) {
+                assert_eq!(find_source_to_destination(source, seed_to_soil), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlapping_inverse() {
+        // The first line wins for 5..10, so 5 doesn't become 100
+        let lines = vec![
+            MapLine {
+                destination: 0,
+                source: 0,
+                source_max: 10,
+            },
+            MapLine {
+                destination: 100,
+                source: 5,
+                source_max: 15,
+            },
+        ];
+        assert_eq!(find_destination_to_sources(100, &lines), vec![100]);
+        // 105 comes from 10 through the second line, and from itself
+        assert_eq!(find_destination_to_sources(105, &lines), vec![10, 105]);
+        assert_eq!(find_destination_to_sources(12, &lines), vec![]);
+    }
+
+    #[test]
+    fn test_seeds_for_location() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+
+        for seed in [79, 14, 55, 13, 82] {
+            let location = location(seed, &maps);
+            assert!(seeds_for_location(location, &maps).contains(&seed));
+        }
+
+        // The part 2 answer comes from seed 82
+        assert_eq!(seeds_for_location(46, &maps), vec![82]);
+    }
+
+    #[test]
+    fn test_reverse_search() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+        assert_eq!(reverse_search_lowest_location(&seeds, &maps), Some(46));
+
+        let seeds: Vec<Range<u64>> = almanac.seeds.iter().map(|s| *s..*s + 1).collect();
+        assert_eq!(reverse_search_lowest_location(&seeds, &maps), Some(35));
+
+        assert_eq!(reverse_search_lowest_location(&[], &maps), None);
+    }
+
+    #[test]
+    fn test_composed_map() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+        let composed = ComposedMap::new(&maps);
+
+        // Sorted lines covering the whole domain without gaps
+        assert_eq!(composed.lines.first().unwrap().source, 0);
+        assert_eq!(composed.lines.last().unwrap().source_max, u64::MAX);
+        for pair in composed.lines.windows(2) {
+            assert_eq!(pair[0].source_max, pair[1].source);
+        }
+
+        for seed in 0..200 {
+            assert_eq!(composed.get(seed), location(seed, &maps), "seed {}", seed);
+        }
+        assert_eq!(composed.get(u64::MAX), u64::MAX);
+        assert_eq!(composed.get(1_000_000), 1_000_000);
+    }
+
+    #[test]
+    fn test_composed_map_overlaps() {
+        let lines = vec![
+            MapLine {
+                destination: 0,
+                source: 0,
+                source_max: 10,
+            },
+            MapLine {
+                destination: 100,
+                source: 5,
+                source_max: 15,
+            },
+        ];
+        let composed = ComposedMap::from_lines(&lines);
+
+        for value in 0..20 {
+            assert_eq!(
+                composed.get(value),
+                find_source_to_destination(value, &lines)
+            );
+        }
+        // 0..10 keeps its numbers, like everything past 15
+        assert_eq!(composed.lines.len(), 3);
+    }
+
+    #[test]
+    fn test_composed_table() {
+        let almanac = parse(ALMANAC).unwrap();
+        let composed = ComposedMap::new(&almanac.path("seed", "soil").unwrap());
+        let table = composed.to_table();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[1].split_whitespace().collect::<Vec<&str>>(),
+            vec!["0", "50", "0", "+0"]
+        );
+        assert_eq!(
+            lines[2].split_whitespace().collect::<Vec<&str>>(),
+            vec!["50", "98", "52", "+2"]
+        );
+        assert_eq!(
+            lines[3].split_whitespace().collect::<Vec<&str>>(),
+            vec!["98", "100", "50", "-48"]
+        );
+        assert_eq!(
+            lines[4].split_whitespace().collect::<Vec<&str>>(),
+            vec!["100", "18446744073709551615", "100", "+0"]
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let almanac = parse(ALMANAC).unwrap();
+        assert!(almanac.warnings.is_empty());
+
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+50 98 2
+52 50 48
+10 96 4";
+        assert_eq!(
+            parse(text).unwrap_err(),
+            AlmanacError::OverlappingSources {
+                line: 6,
+                other_line: 4
+            }
+        );
+
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+50 98 2
+52 50 48
+0 10 0
+60 0 5";
+        let almanac = parse(text).unwrap();
+        assert_eq!(
+            almanac.warnings,
+            vec![
+                AlmanacWarning::ZeroLength { line: 6 },
+                AlmanacWarning::DestinationCollision {
+                    line: 7,
+                    other_line: 5
+                }
+            ]
+        );
+        assert_eq!(
+            almanac.warnings[1].to_string(),
+            "Destination range on line 7 overlaps the one on line 5"
+        );
+
+        // Ranges only need to be disjoint within the same map
+        let text = "seeds: 1 2
+
+seed-to-soil map:
+50 98 2
+
+soil-to-fertilizer map:
+0 98 2";
+        assert!(parse(text).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_brute_force_cancelled() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+
+        let cancelled = AtomicBool::new(true);
+        assert_eq!(
+            brute_force_lowest_location(seeds, &maps, Duration::from_secs(60), &cancelled),
+            None
+        );
+    }
+
+    #[test]
+    fn test_brute_force_chunks() {
+        // More seeds than a single chunk, with progress reported along the way
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+        let seeds = vec![100..BRUTE_FORCE_CHUNK * 2 + 100, 79..93];
+
+        assert_eq!(
+            brute_force_lowest_location(
+                seeds.clone(),
+                &maps,
+                Duration::from_millis(1),
+                &AtomicBool::new(false)
+            ),
+            lowest_location(seeds, &maps)
+        );
+    }
+
+    #[test]
+    fn test_brute_force_near_u64_max() {
+        let text = "seeds: 18446744073709551600 10
+
+seed-to-soil map:
+5 18446744073709551605 10";
+        let almanac = parse(text).unwrap();
+        let maps = almanac.path("seed", "soil").unwrap();
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+
+        assert_eq!(
+            brute_force_lowest_location(
+                seeds,
+                &maps,
+                Duration::from_secs(60),
+                &AtomicBool::new(false)
+            ),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn test_brute_force_sequential() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let lowest = pool.install(|| {
+            brute_force_lowest_location(
+                seeds,
+                &maps,
+                Duration::from_secs(60),
+                &AtomicBool::new(false),
+            )
+        });
+
+        assert_eq!(lowest, Some(46));
+    }
+
+    #[test]
+    fn test_range_stages() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "location").unwrap();
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+        let stages = range_stages(&seeds, &maps);
+
+        assert_eq!(stages.len(), 8);
+        assert_eq!(stages[0], vec![(0, 79..93), (1, 55..68)]);
+        assert_eq!(stages[1], vec![(1, 57..70), (0, 81..95)]);
+
+        // Fragments always add up to the seeds they come from
+        for stage in &stages {
+            let size: u64 = stage.iter().map(|(_, r)| r.end - r.start).sum();
+            assert_eq!(size, 14 + 13);
+        }
+
+        let lowest = stages[7].iter().map(|(_, r)| r.start).min();
+        assert_eq!(lowest, Some(46));
+        assert!(stages[7]
+            .iter()
+            .any(|(origin, r)| *origin == 0 && r.start == 46));
+    }
+
+    #[test]
+    fn test_ranges_exports() {
+        let almanac = parse(ALMANAC).unwrap();
+        let maps = almanac.path("seed", "soil").unwrap();
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+
+        assert_eq!(
+            ranges_text(&seeds, &maps, "seed"),
+            "seed: 2 ranges
+  79..93 (seed range 0)
+  55..68 (seed range 1)
+seed-to-soil:
+  98..100 -> 50..52 (-48)
+  50..98 -> 52..100 (+2)
+soil: 2 ranges
+  57..70 (seed range 1)
+  81..95 (seed range 0)
+"
+        );
+
+        let svg = ranges_svg(&seeds, &maps, "seed");
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<polygon").count(), 2);
+        assert_eq!(svg.matches("<rect").count(), 1 + 4);
+        assert!(svg.contains("<text x=\"40.0\" y=\"20.0\">seed (2)</text>"));
+
+        let text = "seeds: 1 2
+
+a<b-to-\"c\"&d map:
+0 1 2";
+        let almanac = parse(text).unwrap();
+        let maps = almanac.path("a<b", "\"c\"&d").unwrap();
+        let seeds = seed_ranges(&almanac.seeds).unwrap();
+
+        let svg = ranges_svg(&seeds, &maps, "a<b");
+        assert!(svg.contains(">a&lt;b (1)</text>"));
+        assert!(svg.contains("<title>a&lt;b-to-&quot;c&quot;&amp;d: "));
+        assert!(svg.contains("<title>&quot;c&quot;&amp;d: "));
+        assert!(!svg.contains("a<b"));
+    }
 }
@@ -0,0 +1,301 @@
+use core::fmt;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+pub const YEAR: u16 = 2023;
+pub const DEFAULT_ENDPOINT: &str = "https://adventofcode.com";
+
+/// Directory where fetched inputs are stored, `ADVENT_CACHE` or `./.cache`
+pub fn cache_dir() -> PathBuf {
+    env::var_os("ADVENT_CACHE").map_or_else(|| PathBuf::from("./.cache"), PathBuf::from)
+}
+
+/// Website root, `ADVENT_ENDPOINT` or the real website
+pub fn endpoint() -> String {
+    env::var("ADVENT_ENDPOINT").unwrap_or_else(|_| DEFAULT_ENDPOINT.to_string())
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    MissingSession,
+    Status { url: String, status: u16 },
+    Transport { url: String, message: String },
+    Io { path: PathBuf, error: io::Error },
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::MissingSession => write!(f, "ADVENT_SESSION is not set"),
+            FetchError::Status { url, status } => write!(f, "{} answered {}", url, status),
+            FetchError::Transport { url, message } => {
+                write!(f, "Couldn't reach {}: {}", url, message)
+            }
+            FetchError::Io { path, error } => {
+                write!(f, "Couldn't write {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Authenticated access to the puzzle website
+pub struct Client {
+    endpoint: String,
+    session: String,
+}
+
+impl Client {
+    pub fn new(endpoint: &str, session: &str) -> Client {
+        Client {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            session: session.to_string(),
+        }
+    }
+
+    /// Client for `endpoint()` with the `ADVENT_SESSION` cookie
+    pub fn from_env() -> Result<Client, FetchError> {
+        let session = env::var("ADVENT_SESSION").map_err(|_| FetchError::MissingSession)?;
+        Ok(Client::new(&endpoint(), &session))
+    }
+
+    pub fn day_url(&self, day: u8) -> String {
+        format!("{}/{}/day/{}", self.endpoint, YEAR, day)
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        ureq::request(method, url)
+            .set("Cookie", &format!("session={}", self.session))
+            .set("User-Agent", env!("CARGO_PKG_REPOSITORY"))
+    }
+
+    /// Body of a response, failing on non 2xx statuses
+    fn body(
+        &self,
+        url: &str,
+        response: Result<ureq::Response, ureq::Error>,
+    ) -> Result<String, FetchError> {
+        let transport = |message: String| FetchError::Transport {
+            url: url.to_string(),
+            message,
+        };
+
+        match response {
+            Ok(response) => response.into_string().map_err(|e| transport(e.to_string())),
+            Err(ureq::Error::Status(status, _)) => Err(FetchError::Status {
+                url: url.to_string(),
+                status,
+            }),
+            Err(err) => Err(transport(err.to_string())),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Result<String, FetchError> {
+        self.body(url, self.request("GET", url).call())
+    }
+
+    pub fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<String, FetchError> {
+        self.body(url, self.request("POST", url).send_form(form))
+    }
+}
+
+/// Text of every `<pre><code>` block of a puzzle page
+pub fn examples(page: &str) -> Vec<String> {
+    let mut blocks = vec![];
+    let mut rest = page;
+
+    while let Some(start) = rest.find("<pre><code>") {
+        rest = &rest[start + "<pre><code>".len()..];
+        let Some(end) = rest.find("</code></pre>") else {
+            break;
+        };
+        blocks.push(unescape(&strip_tags(&rest[..end])));
+        rest = &rest[end..];
+    }
+
+    blocks
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Files of a day in the cache, and whether they were downloaded just now
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Fetched {
+    pub input: PathBuf,
+    pub examples: Vec<PathBuf>,
+    pub downloaded: bool,
+}
+
+fn example_paths(cache: &Path, day: u8) -> Vec<PathBuf> {
+    (1..)
+        .map(|i| cache.join(format!("day_{}_ex_{}.txt", day, i)))
+        .take_while(|path| path.exists())
+        .collect()
+}
+
+fn write(path: &Path, text: &str) -> Result<(), FetchError> {
+    fs::write(path, text).map_err(|error| FetchError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Input and examples of `day` in `cache`, only downloaded when missing
+pub fn fetch(client: &Client, day: u8, cache: &Path) -> Result<Fetched, FetchError> {
+    let input = cache.join(format!("day_{}.txt", day));
+
+    // The input is written last so its presence means the day is complete
+    if input.exists() {
+        return Ok(Fetched {
+            examples: example_paths(cache, day),
+            input,
+            downloaded: false,
+        });
+    }
+
+    fs::create_dir_all(cache).map_err(|error| FetchError::Io {
+        path: cache.to_path_buf(),
+        error,
+    })?;
+
+    let page = client.get(&client.day_url(day))?;
+    let text = client.get(&format!("{}/input", client.day_url(day)))?;
+
+    let mut examples = vec![];
+    for (i, example) in self::examples(&page).iter().enumerate() {
+        let path = cache.join(format!("day_{}_ex_{}.txt", day, i + 1));
+        write(&path, example)?;
+        examples.push(path);
+    }
+    write(&input, &text)?;
+
+    Ok(Fetched {
+        input,
+        examples,
+        downloaded: true,
+    })
+}
+
+pub fn run(day: u8) {
+    let client = Client::from_env().unwrap_or_else(|err| panic!("{}", err));
+
+    let fetched = match fetch(&client, day, &cache_dir()) {
+        Ok(fetched) => fetched,
+        Err(err) => panic!("Unable to fetch day {}: {}", day, err),
+    };
+
+    let state = if fetched.downloaded {
+        "Downloaded"
+    } else {
+        "Already cached"
+    };
+    info!("{} {}", state, fetched.input.display());
+    for example in &fetched.examples {
+        info!("{} {}", state, example.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server;
+
+    const PAGE: &str = "<article><p>For example:</p>
+<pre><code>467..114..
+...*......
+</code></pre><p>Then <code>4361</code>.</p>
+<pre><code>a &lt;<em>b</em>&gt; &amp; c
+</code></pre></article>";
+
+    fn temp_cache(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("advent-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(
+            examples(PAGE),
+            vec!["467..114..\n...*......\n", "a <b> & c\n"]
+        );
+        assert!(examples("<p>No example</p>").is_empty());
+    }
+
+    #[test]
+    fn test_fetch_once() {
+        let (endpoint, server) = mock_server::serve(2, |request| match request.path.as_str() {
+            "/2023/day/3" => (200, PAGE.to_string()),
+            "/2023/day/3/input" => (200, "real input\n".to_string()),
+            _ => (404, String::new()),
+        });
+        let client = Client::new(&endpoint, "secret");
+        let cache = temp_cache("fetch");
+
+        let fetched = fetch(&client, 3, &cache).unwrap();
+        assert!(fetched.downloaded);
+        assert_eq!(fs::read_to_string(&fetched.input).unwrap(), "real input\n");
+        assert_eq!(
+            fetched.examples,
+            vec![cache.join("day_3_ex_1.txt"), cache.join("day_3_ex_2.txt")]
+        );
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests
+            .iter()
+            .all(|r| r.method == "GET" && r.header("Cookie") == Some("session=secret")));
+
+        // The server is gone, a second fetch must only read the cache
+        let cached = fetch(&client, 3, &cache).unwrap();
+        assert!(!cached.downloaded);
+        assert_eq!(cached.input, fetched.input);
+        assert_eq!(cached.examples, fetched.examples);
+
+        fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_error() {
+        let (endpoint, server) = mock_server::serve(1, |_| (400, "log in".to_string()));
+        let client = Client::new(&endpoint, "expired");
+        let cache = temp_cache("fetch-error");
+
+        let err = fetch(&client, 25, &cache).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{}/2023/day/25 answered 400", endpoint)
+        );
+        assert!(!cache.join("day_25.txt").exists());
+
+        server.join().unwrap();
+        fs::remove_dir_all(&cache).unwrap();
+    }
+}
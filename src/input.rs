@@ -0,0 +1,238 @@
+use core::fmt;
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use crate::fetch;
+
+/// Where the puzzle input of a day is read from
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub enum Source {
+    /// Input compiled in with the `embed-inputs` feature, `./src/inputs/day_N.txt`
+    /// otherwise, or the fetched one when there is none
+    #[default]
+    Default,
+    Path(PathBuf),
+    Stdin,
+    /// Text already in memory, e.g. an example
+    Embedded(&'static str),
+}
+
+impl Source {
+    /// Source from the `--input` command line value, `-` being stdin
+    pub fn from_arg(arg: Option<&str>) -> Source {
+        match arg {
+            None => Source::Default,
+            Some("-") => Source::Stdin,
+            Some(path) => Source::Path(PathBuf::from(path)),
+        }
+    }
+}
+
+/// Inputs compiled into the binary, so it runs from any directory
+#[cfg(feature = "embed-inputs")]
+fn embedded(day: u8) -> Option<&'static str> {
+    match day {
+        1 => Some(include_str!("inputs/day_1.txt")),
+        2 => Some(include_str!("inputs/day_2.txt")),
+        3 => Some(include_str!("inputs/day_3.txt")),
+        4 => Some(include_str!("inputs/day_4.txt")),
+        5 => Some(include_str!("inputs/day_5.txt")),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "embed-inputs"))]
+fn embedded(_day: u8) -> Option<&'static str> {
+    None
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Io { name: String, error: io::Error },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io { name, error } => write!(f, "Couldn't read {}: {}", name, error),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Puzzle input, with CRLF line endings and trailing newlines removed
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Input {
+    name: String,
+    text: String,
+}
+
+impl Input {
+    /// Input of `day` read from `source`
+    pub fn load(day: u8, source: &Source) -> Result<Input, InputError> {
+        if let (Source::Default, Some(text)) = (source, embedded(day)) {
+            return Input::load(day, &Source::Embedded(text));
+        }
+
+        let (name, text) = match source {
+            Source::Default => {
+                let mut path = PathBuf::from(format!("./src/inputs/day_{}.txt", day));
+                let cached = fetch::cache_dir().join(format!("day_{}.txt", day));
+                if !path.exists() && cached.exists() {
+                    path = cached;
+                }
+                let text = fs::read_to_string(&path);
+                (path.display().to_string(), text)
+            }
+            Source::Path(path) => (path.display().to_string(), fs::read_to_string(path)),
+            Source::Stdin => {
+                let mut text = String::new();
+                let read = io::stdin().read_to_string(&mut text).map(|_| text);
+                ("stdin".to_string(), read)
+            }
+            Source::Embedded(text) => (format!("day {} embedded input", day), Ok(text.to_string())),
+        };
+
+        match text {
+            Ok(text) => Ok(Input::new(name, &text)),
+            Err(error) => Err(InputError::Io { name, error }),
+        }
+    }
+
+    /// Input named `name` made of `text`
+    pub fn new(name: impl Into<String>, text: &str) -> Input {
+        Input {
+            name: name.into(),
+            text: text
+                .replace("\r\n", "\n")
+                .trim_end_matches('\n')
+                .to_string(),
+        }
+    }
+
+    /// Where the input comes from, for error messages
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.lines()
+    }
+
+    /// Characters of each line, rows possibly having different lengths
+    pub fn grid(&self) -> Vec<Vec<char>> {
+        self.lines().map(|line| line.chars().collect()).collect()
+    }
+}
+
+/// Day of a `day_N_ex_M.txt` example input
+pub fn example_day(file_name: &str) -> Option<u8> {
+    let name = file_name.strip_prefix("day_")?.strip_suffix(".txt")?;
+    let (day, example) = name.split_once("_ex_")?;
+    example.parse::<u8>().ok()?;
+    day.parse().ok()
+}
+
+/// Expected answers of a `.answers` file, one `part: answer` per line
+pub fn parse_answers(text: &str) -> Result<Vec<(u8, String)>, String> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (part, answer) = line
+                .split_once(':')
+                .ok_or_else(|| format!("Expected `part: answer`, got {:?}", line))?;
+            let part = part
+                .trim()
+                .parse()
+                .map_err(|_| format!("Part must be a number, got {:?}", part))?;
+            Ok((part, answer.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        let input = Input::new("test", "ab\r\ncd\r\n\r\nef\r\n\n\n");
+
+        assert_eq!(input.text(), "ab\ncd\n\nef");
+        assert_eq!(
+            input.lines().collect::<Vec<_>>(),
+            vec!["ab", "cd", "", "ef"]
+        );
+        assert_eq!(
+            input.grid(),
+            vec![vec!['a', 'b'], vec!['c', 'd'], vec![], vec!['e', 'f']]
+        );
+    }
+
+    #[test]
+    fn test_sources() {
+        assert_eq!(Source::from_arg(None), Source::Default);
+        assert_eq!(Source::from_arg(Some("-")), Source::Stdin);
+        assert_eq!(
+            Source::from_arg(Some("day.txt")),
+            Source::Path(PathBuf::from("day.txt"))
+        );
+
+        let input = Input::load(5, &Source::Embedded("seeds: 1 2\r\n")).unwrap();
+        assert_eq!(input.text(), "seeds: 1 2");
+        assert_eq!(input.name(), "day 5 embedded input");
+
+        let input = Input::load(1, &Source::Path("./src/inputs/day_1_ex_2.txt".into())).unwrap();
+        assert_eq!(input.lines().count(), 7);
+    }
+
+    #[cfg(feature = "embed-inputs")]
+    #[test]
+    fn test_embedded_default() {
+        let input = Input::load(4, &Source::Default).unwrap();
+        assert_eq!(input.name(), "day 4 embedded input");
+        assert_eq!(
+            input.text(),
+            Input::load(4, &Source::Path("./src/inputs/day_4.txt".into()))
+                .unwrap()
+                .text()
+        );
+
+        assert!(Input::load(9, &Source::Default).is_err());
+    }
+
+    #[test]
+    fn test_example_day() {
+        assert_eq!(example_day("day_1_ex_2.txt"), Some(1));
+        assert_eq!(example_day("day_12_ex_1.txt"), Some(12));
+        assert_eq!(example_day("day_1.txt"), None);
+        assert_eq!(example_day("day_1_ex_2.answers"), None);
+    }
+
+    #[test]
+    fn test_parse_answers() {
+        assert_eq!(
+            parse_answers("1: 13\n\n2:30\n"),
+            Ok(vec![(1, "13".to_string()), (2, "30".to_string())])
+        );
+        assert!(parse_answers("13").is_err());
+        assert!(parse_answers("one: 13").is_err());
+    }
+
+    #[test]
+    fn test_read_error() {
+        let err = Input::load(1, &Source::Path("./src/inputs/missing.txt".into())).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .starts_with("Couldn't read ./src/inputs/missing.txt: "));
+    }
+}
@@ -0,0 +1,28 @@
+pub mod day_1;
+pub mod day_2;
+pub mod day_3;
+pub mod day_4;
+pub mod day_5;
+pub mod fetch;
+pub mod input;
+#[cfg(test)]
+mod mock_server;
+pub mod render;
+pub mod submit;
+pub mod watch;
+
+use input::Input;
+
+/// Answers of the given day for `input`, as `(part, answer)` pairs
+pub fn answers(day: u8, input: &Input) -> Option<Vec<(u8, String)>> {
+    let _span = tracing::info_span!("day", day).entered();
+
+    match day {
+        1 => Some(day_1::answers(input)),
+        2 => Some(day_2::answers(input)),
+        3 => Some(day_3::answers(input)),
+        4 => Some(day_4::answers(input)),
+        5 => Some(day_5::answers(input)),
+        _ => None,
+    }
+}
@@ -1,6 +1,151 @@
-mod day_5;
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+
+use advent::{
+    day_1, day_2, day_3, day_4, day_5, fetch,
+    input::{Input, Source},
+    submit, watch,
+};
+use log::info;
+use tracing_chrome::ChromeLayerBuilder;
+use tracing_subscriber::{filter::filter_fn, fmt::format::FmtSpan, prelude::*, EnvFilter};
+
+/// Day number given as `arg`, 5 by default
+fn day_argument(arg: Option<&String>) -> u8 {
+    arg.map(|arg| arg.parse::<u8>().expect("Day must be a number"))
+        .unwrap_or(5)
+}
+
+/// Value following `name` in the command line arguments
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|value| value.as_str())
+}
 
 fn main() {
-    env_logger::init();
-    day_5::run();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Spans are logged with their duration when closed, and kept as a chrome
+    // trace (chrome://tracing, ui.perfetto.dev) when `--trace` is given
+    let (chrome, _trace_guard) = match option_value(&args, "--trace") {
+        Some(path) => {
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .file(path)
+                .include_args(true)
+                .build();
+            (
+                Some(layer.with_filter(filter_fn(|metadata| metadata.is_span()))),
+                Some(guard),
+            )
+        }
+        None => (None, None),
+    };
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_ansi(io::stderr().is_terminal())
+                .with_span_events(FmtSpan::CLOSE)
+                .with_filter(
+                    // Only errors and the outcomes of `fetch`, `submit`, `watch` and
+                    // `check` by default
+                    EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                        EnvFilter::new(
+                            "error,advent::fetch=info,advent::submit=info,advent::watch=info",
+                        )
+                    }),
+                ),
+        )
+        .with(chrome)
+        .init();
+
+    if args.first().is_some_and(|arg| arg == "fetch") {
+        return fetch::run(day_argument(args.get(1)));
+    }
+
+    if args.first().is_some_and(|arg| arg == "watch") {
+        return watch::run(day_argument(args.get(1)));
+    }
+
+    if args.first().is_some_and(|arg| arg == "check") {
+        return watch::run_check(day_argument(args.get(1)));
+    }
+
+    if args.first().is_some_and(|arg| arg == "submit") {
+        let day = day_argument(args.get(1));
+        let part: u8 = args
+            .get(2)
+            .expect("Part to submit is missing")
+            .parse()
+            .expect("Part must be a number");
+        let source = Source::from_arg(option_value(&args, "--input"));
+        let input = Input::load(day, &source).unwrap_or_else(|err| panic!("{}", err));
+        return submit::run(day, part, &input);
+    }
+
+    let day = day_argument(args.first());
+
+    // Every parallel day runs on rayon's global pool, 1 thread being sequential
+    if let Some(threads) = option_value(&args, "--threads") {
+        let threads: usize = threads.parse().expect("Threads must be a number");
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("Couldn't build the thread pool");
+    }
+    info!("Rayon threads: {}", rayon::current_num_threads());
+
+    if !(1..=5).contains(&day) {
+        panic!("Day {} is not available", day);
+    }
+
+    let source = Source::from_arg(option_value(&args, "--input"));
+    let input = Input::load(day, &source).unwrap_or_else(|err| panic!("{}", err));
+
+    let _span = tracing::info_span!("day", day).entered();
+
+    match day {
+        1 => day_1::run(&input),
+        2 => day_2::run(&input),
+        3 => day_3::run(
+            &input,
+            option_value(&args, "--render").map(|f| f.parse().unwrap()),
+            option_value(&args, "--output").map(PathBuf::from),
+        ),
+        4 => day_4::run(
+            &input,
+            option_value(&args, "--past-end")
+                .map(|p| p.parse().unwrap())
+                .unwrap_or_default(),
+            option_value(&args, "--explain").map(|f| f.parse().unwrap()),
+            option_value(&args, "--output").map(PathBuf::from),
+        ),
+        5 => {
+            let composed = args.iter().any(|arg| arg == "--composed");
+            let export = match (composed, option_value(&args, "--ranges")) {
+                (true, Some(_)) => panic!("--composed and --ranges can't be exported together"),
+                (true, None) => Some(day_5::Export::Composed),
+                (false, ranges) => ranges.map(|f| day_5::Export::Ranges(f.parse().unwrap())),
+            };
+
+            day_5::run(
+                &input,
+                &day_5::Options {
+                    from: option_value(&args, "--from").unwrap_or("seed").to_string(),
+                    to: option_value(&args, "--to")
+                        .unwrap_or("location")
+                        .to_string(),
+                    brute_force: args.iter().any(|arg| arg == "--brute-force"),
+                    reverse_search: args.iter().any(|arg| arg == "--reverse-search"),
+                    export,
+                    output: option_value(&args, "--output").map(PathBuf::from),
+                },
+            )
+        }
+        _ => panic!("Day {} is not available", day),
+    }
 }
@@ -0,0 +1,530 @@
+use core::fmt;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+
+use crate::{
+    fetch::{self, Client, FetchError},
+    input::Input,
+};
+
+/// Seconds to wait after a wrong answer when the response doesn't say
+const DEFAULT_WAIT: u64 = 60;
+
+/// Outcome of a submission, as told by the website
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Verdict {
+    Right,
+    Wrong,
+    TooHigh,
+    TooLow,
+    /// Submitted during the cooldown of a previous answer
+    TooRecent,
+    /// Part already solved, or part 2 before part 1
+    WrongLevel,
+}
+
+impl Verdict {
+    fn is_wrong(&self) -> bool {
+        matches!(self, Verdict::Wrong | Verdict::TooHigh | Verdict::TooLow)
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Verdict::Right => "right",
+            Verdict::Wrong => "wrong",
+            Verdict::TooHigh => "too-high",
+            Verdict::TooLow => "too-low",
+            Verdict::TooRecent => "too-recent",
+            Verdict::WrongLevel => "wrong-level",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Verdict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right" => Ok(Verdict::Right),
+            "wrong" => Ok(Verdict::Wrong),
+            "too-high" => Ok(Verdict::TooHigh),
+            "too-low" => Ok(Verdict::TooLow),
+            "too-recent" => Ok(Verdict::TooRecent),
+            "wrong-level" => Ok(Verdict::WrongLevel),
+            _ => Err(format!("Unknown verdict: {}", s)),
+        }
+    }
+}
+
+/// Seconds of a `1m 5s` like duration
+fn parse_wait(text: &str) -> Option<u64> {
+    text.split_whitespace()
+        .map(|token| {
+            if let Some(minutes) = token.strip_suffix('m') {
+                minutes.parse::<u64>().ok().map(|m| m * 60)
+            } else if let Some(seconds) = token.strip_suffix('s') {
+                seconds.parse::<u64>().ok()
+            } else {
+                None
+            }
+        })
+        .sum()
+}
+
+/// Verdict of the answer page and the seconds to wait before the next submission
+pub fn parse_response(page: &str) -> Option<(Verdict, u64)> {
+    if page.contains("That's the right answer") {
+        return Some((Verdict::Right, 0));
+    }
+
+    if page.contains("You don't seem to be solving the right level") {
+        return Some((Verdict::WrongLevel, 0));
+    }
+
+    if page.contains("You gave an answer too recently") {
+        let wait = page
+            .split_once("You have ")
+            .and_then(|(_, rest)| rest.split_once(" left to wait"))
+            .and_then(|(wait, _)| parse_wait(wait))
+            .unwrap_or(DEFAULT_WAIT);
+        return Some((Verdict::TooRecent, wait));
+    }
+
+    if page.contains("That's not the right answer") {
+        let verdict = if page.contains("your answer is too high") {
+            Verdict::TooHigh
+        } else if page.contains("your answer is too low") {
+            Verdict::TooLow
+        } else {
+            Verdict::Wrong
+        };
+        // The site writes both "Please wait" and "..., please wait"
+        let wait = page
+            .to_lowercase()
+            .split_once("please wait ")
+            .and_then(|(_, rest)| rest.split_once(" before trying again"))
+            .and_then(|(wait, _)| match wait {
+                "one minute" => Some(60),
+                wait => wait
+                    .strip_suffix(" minutes")
+                    .and_then(|m| m.parse::<u64>().ok())
+                    .map(|m| m * 60),
+            })
+            .unwrap_or(DEFAULT_WAIT);
+        return Some((verdict, wait));
+    }
+
+    None
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Submission {
+    pub day: u8,
+    pub part: u8,
+    pub answer: String,
+    pub verdict: Verdict,
+    /// Unix time of the submission
+    pub at: u64,
+    /// Unix time before which nothing can be submitted
+    pub retry_at: u64,
+}
+
+#[derive(Debug)]
+pub enum SubmitError {
+    Fetch(FetchError),
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    InvalidRegistry {
+        line: usize,
+    },
+    MissingAnswer {
+        day: u8,
+        part: u8,
+    },
+    /// Empty or containing whitespace, which the registry can't store
+    InvalidAnswer(String),
+    AlreadySolved {
+        answer: String,
+    },
+    KnownWrong {
+        answer: String,
+        verdict: Verdict,
+    },
+    Cooldown {
+        remaining: u64,
+    },
+    UnknownResponse,
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::Fetch(err) => write!(f, "{}", err),
+            SubmitError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            SubmitError::InvalidRegistry { line } => {
+                write!(f, "Invalid answers registry line {}", line)
+            }
+            SubmitError::MissingAnswer { day, part } => {
+                write!(f, "Day {} doesn't compute part {}", day, part)
+            }
+            SubmitError::InvalidAnswer(answer) => write!(f, "Invalid answer {:?}", answer),
+            SubmitError::AlreadySolved { answer } => {
+                write!(f, "Already solved, the answer is {}", answer)
+            }
+            SubmitError::KnownWrong { answer, verdict } => {
+                write!(f, "{} is known to be wrong ({})", answer, verdict)
+            }
+            SubmitError::Cooldown { remaining } => {
+                write!(f, "Wait {}s before submitting again", remaining)
+            }
+            SubmitError::UnknownResponse => write!(f, "Couldn't understand the response"),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+impl From<FetchError> for SubmitError {
+    fn from(err: FetchError) -> Self {
+        SubmitError::Fetch(err)
+    }
+}
+
+/// Every submission made, one `day part answer verdict at retry_at` per line
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Registry {
+    path: PathBuf,
+    pub submissions: Vec<Submission>,
+}
+
+impl Registry {
+    /// Registry stored at `path`, empty when it doesn't exist yet
+    pub fn load(path: &Path) -> Result<Registry, SubmitError> {
+        let mut registry = Registry {
+            path: path.to_path_buf(),
+            submissions: vec![],
+        };
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(registry),
+            Err(error) => {
+                return Err(SubmitError::Io {
+                    path: path.to_path_buf(),
+                    error,
+                })
+            }
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let invalid = || SubmitError::InvalidRegistry { line: i + 1 };
+            let [day, part, answer, verdict, at, retry_at] =
+                line.split(' ').collect::<Vec<_>>()[..]
+            else {
+                return Err(invalid());
+            };
+
+            registry.submissions.push(Submission {
+                day: day.parse().map_err(|_| invalid())?,
+                part: part.parse().map_err(|_| invalid())?,
+                answer: answer.to_string(),
+                verdict: verdict.parse().map_err(|_| invalid())?,
+                at: at.parse().map_err(|_| invalid())?,
+                retry_at: retry_at.parse().map_err(|_| invalid())?,
+            });
+        }
+
+        Ok(registry)
+    }
+
+    pub fn save(&self) -> Result<(), SubmitError> {
+        let io_error = |error| SubmitError::Io {
+            path: self.path.clone(),
+            error,
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+
+        let text: String = self
+            .submissions
+            .iter()
+            .map(|s| {
+                format!(
+                    "{} {} {} {} {} {}\n",
+                    s.day, s.part, s.answer, s.verdict, s.at, s.retry_at
+                )
+            })
+            .collect();
+        fs::write(&self.path, text).map_err(io_error)
+    }
+
+    /// Right answer of a part, if it was found
+    pub fn right_answer(&self, day: u8, part: u8) -> Option<&str> {
+        self.submissions
+            .iter()
+            .find(|s| s.day == day && s.part == part && s.verdict == Verdict::Right)
+            .map(|s| s.answer.as_str())
+    }
+
+    /// Why `answer` can't be right, given the previous submissions of the part
+    fn known_wrong(&self, day: u8, part: u8, answer: &str) -> Option<&Submission> {
+        let value = answer.parse::<i128>().ok();
+
+        self.submissions
+            .iter()
+            .filter(|s| s.day == day && s.part == part && s.verdict.is_wrong())
+            .find(|s| {
+                let bound = s.answer.parse::<i128>().ok();
+                match (s.verdict, value, bound) {
+                    (Verdict::TooHigh, Some(value), Some(bound)) => value >= bound,
+                    (Verdict::TooLow, Some(value), Some(bound)) => value <= bound,
+                    _ => s.answer == answer,
+                }
+            })
+    }
+
+    /// Seconds left before the website accepts answers again
+    fn cooldown(&self, now: u64) -> u64 {
+        self.submissions
+            .iter()
+            .map(|s| s.retry_at.saturating_sub(now))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Submit `answer` unless the registry knows it's pointless, then record the verdict
+pub fn submit(
+    client: &Client,
+    registry: &mut Registry,
+    day: u8,
+    part: u8,
+    answer: &str,
+    now: u64,
+) -> Result<Verdict, SubmitError> {
+    if answer.is_empty() || answer.contains(char::is_whitespace) {
+        return Err(SubmitError::InvalidAnswer(answer.to_string()));
+    }
+
+    if let Some(right) = registry.right_answer(day, part) {
+        return Err(SubmitError::AlreadySolved {
+            answer: right.to_string(),
+        });
+    }
+
+    if let Some(wrong) = registry.known_wrong(day, part, answer) {
+        return Err(SubmitError::KnownWrong {
+            answer: wrong.answer.clone(),
+            verdict: wrong.verdict,
+        });
+    }
+
+    let remaining = registry.cooldown(now);
+    if remaining > 0 {
+        return Err(SubmitError::Cooldown { remaining });
+    }
+
+    let url = format!("{}/answer", client.day_url(day));
+    let page = client.post_form(&url, &[("level", &part.to_string()), ("answer", answer)])?;
+    let (verdict, wait) = parse_response(&page).ok_or(SubmitError::UnknownResponse)?;
+
+    registry.submissions.push(Submission {
+        day,
+        part,
+        answer: answer.to_string(),
+        verdict,
+        at: now,
+        retry_at: now + wait,
+    });
+    registry.save()?;
+
+    Ok(verdict)
+}
+
+pub fn registry_path() -> PathBuf {
+    fetch::cache_dir().join("answers.txt")
+}
+
+pub fn run(day: u8, part: u8, input: &Input) {
+    let answer = crate::answers(day, input)
+        .and_then(|answers| answers.into_iter().find(|(p, _)| *p == part))
+        .map(|(_, answer)| answer)
+        .unwrap_or_else(|| panic!("{}", SubmitError::MissingAnswer { day, part }));
+    info!("Day {} part {}: {}", day, part, answer);
+
+    let client = Client::from_env().unwrap_or_else(|err| panic!("{}", err));
+    let mut registry = Registry::load(&registry_path()).unwrap_or_else(|err| panic!("{}", err));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Clock is before 1970")
+        .as_secs();
+
+    match submit(&client, &mut registry, day, part, &answer, now) {
+        Ok(Verdict::Right) => info!("{} is the right answer", answer),
+        Ok(verdict) => warn!("{} is {}", answer, verdict),
+        Err(err) => panic!("Not submitted: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server;
+    use std::env;
+
+    const RIGHT: &str =
+        "<article><p>That's the right answer! You are one gold star closer.</p></article>";
+    const TOO_HIGH: &str = "<article><p>That's not the right answer; your answer is too high.  \
+        If you're stuck, make sure you're using the full input data; there are also some general \
+        tips on the <a href=\"/2023/about\">about page</a>, or you can ask for hints on the \
+        <a href=\"https://www.reddit.com/r/adventofcode/\" target=\"_blank\">subreddit</a>.  \
+        Please wait one minute before trying again. <a href=\"/2023/day/4\">[Return to Day 4]</a>\
+        </p></article>";
+    const WRONG: &str = "<article><p>That's not the right answer.  If you're stuck, make sure \
+        you're using the full input data; there are also some general tips on the \
+        <a href=\"/2023/about\">about page</a>, or you can ask for hints on the \
+        <a href=\"https://www.reddit.com/r/adventofcode/\" target=\"_blank\">subreddit</a>.  \
+        Because you have guessed incorrectly 4 times on this puzzle, please wait 5 minutes \
+        before trying again. <a href=\"/2023/day/4\">[Return to Day 4]</a></p></article>";
+    const TOO_LOW: &str = "<article><p>That's not the right answer; your answer is too low.  \
+        If you're stuck, make sure you're using the full input data.  \
+        Please wait 10 minutes before trying again. <a href=\"/2023/day/4\">[Return to Day 4]</a>\
+        </p></article>";
+    const TOO_RECENT: &str = "<article><p>You gave an answer too recently; you have to wait \
+        after submitting an answer before trying again.  You have 1m 5s left to wait.</p></article>";
+
+    fn temp_registry(name: &str) -> Registry {
+        let path = env::temp_dir().join(format!("advent-{}-{}.txt", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        Registry::load(&path).unwrap()
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(parse_response(RIGHT), Some((Verdict::Right, 0)));
+        assert_eq!(parse_response(TOO_HIGH), Some((Verdict::TooHigh, 60)));
+        assert_eq!(parse_response(WRONG), Some((Verdict::Wrong, 300)));
+        assert_eq!(parse_response(TOO_LOW), Some((Verdict::TooLow, 600)));
+        assert_eq!(parse_response(TOO_RECENT), Some((Verdict::TooRecent, 65)));
+        assert_eq!(
+            parse_response("You don't seem to be solving the right level."),
+            Some((Verdict::WrongLevel, 0))
+        );
+        assert_eq!(parse_response("<html>Something else</html>"), None);
+    }
+
+    #[test]
+    fn test_submit() {
+        let (endpoint, server) = mock_server::serve(2, |request| {
+            if request.body.contains("answer=100") {
+                (200, TOO_HIGH.to_string())
+            } else {
+                (200, RIGHT.to_string())
+            }
+        });
+        let client = Client::new(&endpoint, "secret");
+        let mut registry = temp_registry("submit");
+
+        let verdict = submit(&client, &mut registry, 4, 1, "100", 1000).unwrap();
+        assert_eq!(verdict, Verdict::TooHigh);
+
+        // Refused without reaching the server
+        assert!(matches!(
+            submit(&client, &mut registry, 4, 1, "120", 1100),
+            Err(SubmitError::KnownWrong {
+                verdict: Verdict::TooHigh,
+                ..
+            })
+        ));
+        assert!(matches!(
+            submit(&client, &mut registry, 4, 1, "13", 1030),
+            Err(SubmitError::Cooldown { remaining: 30 })
+        ));
+
+        let verdict = submit(&client, &mut registry, 4, 1, "13", 1060).unwrap();
+        assert_eq!(verdict, Verdict::Right);
+        assert!(matches!(
+            submit(&client, &mut registry, 4, 1, "13", 2000),
+            Err(SubmitError::AlreadySolved { .. })
+        ));
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/2023/day/4/answer");
+        assert_eq!(requests[0].body, "level=1&answer=100");
+
+        let saved = Registry::load(&registry.path).unwrap();
+        assert_eq!(saved, registry);
+        assert_eq!(saved.right_answer(4, 1), Some("13"));
+        assert_eq!(
+            fs::read_to_string(&registry.path).unwrap(),
+            "4 1 100 too-high 1000 1060\n4 1 13 right 1060 1060\n"
+        );
+
+        fs::remove_file(&registry.path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_answer() {
+        // Never reached: invalid answers are refused before posting
+        let client = Client::new("http://127.0.0.1:9", "secret");
+        let mut registry = temp_registry("invalid-answer");
+        registry.submissions.push(Submission {
+            day: 5,
+            part: 1,
+            answer: "35".to_string(),
+            verdict: Verdict::Right,
+            at: 0,
+            retry_at: 0,
+        });
+        registry.save().unwrap();
+
+        for answer in ["", " ", "1 2", "3\n"] {
+            assert!(matches!(
+                submit(&client, &mut registry, 5, 2, answer, 100),
+                Err(SubmitError::InvalidAnswer(_))
+            ));
+        }
+
+        assert_eq!(registry.submissions.len(), 1);
+        assert_eq!(Registry::load(&registry.path).unwrap(), registry);
+
+        fs::remove_file(&registry.path).unwrap();
+    }
+
+    #[test]
+    fn test_known_wrong() {
+        let mut registry = temp_registry("known-wrong");
+        for (answer, verdict) in [("10", Verdict::TooLow), ("abc", Verdict::Wrong)] {
+            registry.submissions.push(Submission {
+                day: 1,
+                part: 2,
+                answer: answer.to_string(),
+                verdict,
+                at: 0,
+                retry_at: 60,
+            });
+        }
+
+        assert!(registry.known_wrong(1, 2, "9").is_some());
+        assert!(registry.known_wrong(1, 2, "10").is_some());
+        assert!(registry.known_wrong(1, 2, "abc").is_some());
+        assert!(registry.known_wrong(1, 2, "11").is_none());
+        assert!(registry.known_wrong(1, 1, "9").is_none());
+        assert_eq!(registry.cooldown(30), 30);
+        assert_eq!(registry.cooldown(90), 0);
+    }
+}
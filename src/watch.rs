@@ -0,0 +1,284 @@
+use std::{
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use log::{error, info, warn};
+
+use crate::{
+    fetch,
+    input::{example_day, parse_answers, Input, Source},
+    submit::{self, Registry},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Source of `day` and its input, example and answers files in `dirs`
+pub fn watched_files(day: u8, dirs: &[&Path]) -> Vec<PathBuf> {
+    let mut files = vec![PathBuf::from(format!("./src/day_{}.rs", day))];
+
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let prefix = format!("day_{}.", day);
+        let example_prefix = format!("day_{}_ex_", day);
+
+        files.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    name.starts_with(&prefix) || name.starts_with(&example_prefix)
+                }),
+        );
+    }
+
+    files.sort();
+    files
+}
+
+/// Modification time of each file, `None` once removed
+fn snapshot(files: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    files
+        .iter()
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+            (path.clone(), modified)
+        })
+        .collect()
+}
+
+/// Files added, removed or modified between two snapshots
+fn changes(
+    before: &[(PathBuf, Option<SystemTime>)],
+    after: &[(PathBuf, Option<SystemTime>)],
+) -> Vec<PathBuf> {
+    let added_or_modified = after.iter().filter(|file| !before.contains(file));
+    let removed = before
+        .iter()
+        .filter(|(path, _)| !after.iter().any(|(p, _)| p == path));
+
+    added_or_modified
+        .chain(removed)
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Answer of a part compared to the known one
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Outcome {
+    pub part: u8,
+    pub answer: String,
+    pub expected: Option<String>,
+}
+
+impl Outcome {
+    pub fn status(&self) -> &'static str {
+        match &self.expected {
+            Some(expected) if *expected == self.answer => "pass",
+            Some(_) => "FAIL",
+            None => "?",
+        }
+    }
+}
+
+/// Answers of `day` for `input`, a panic of the day being reported as an error
+pub fn check(day: u8, input: &Input, expected: &[(u8, String)]) -> Result<Vec<Outcome>, String> {
+    let answers = panic::catch_unwind(AssertUnwindSafe(|| crate::answers(day, input)));
+
+    let answers = match answers {
+        Ok(Some(answers)) => answers,
+        Ok(None) => return Err(format!("Day {} isn't solved", day)),
+        Err(err) => {
+            return Err(err
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| err.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "panicked".to_string()))
+        }
+    };
+
+    Ok(answers
+        .into_iter()
+        .map(|(part, answer)| Outcome {
+            part,
+            expected: expected
+                .iter()
+                .find(|(p, _)| *p == part)
+                .map(|(_, a)| a.clone()),
+            answer,
+        })
+        .collect())
+}
+
+fn log_outcomes(name: &str, outcomes: Result<Vec<Outcome>, String>) {
+    match outcomes {
+        Ok(outcomes) => {
+            for outcome in outcomes {
+                match (outcome.status(), &outcome.expected) {
+                    ("FAIL", Some(expected)) => warn!(
+                        "{} part {}: {} FAIL, expected {}",
+                        name, outcome.part, outcome.answer, expected
+                    ),
+                    (status, _) => {
+                        info!(
+                            "{} part {}: {} {}",
+                            name, outcome.part, outcome.answer, status
+                        )
+                    }
+                }
+            }
+        }
+        Err(err) => error!("{}: {}", name, err),
+    }
+}
+
+/// Check the examples of `day` against their `.answers` and the real input
+/// against the right answers of the submissions registry
+pub fn run_check(day: u8) {
+    let cache = fetch::cache_dir();
+    let mut examples: Vec<PathBuf> = [Path::new("./src/inputs"), cache.as_path()]
+        .iter()
+        .flat_map(|dir| fs::read_dir(dir).into_iter().flatten())
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            example_day(&path.file_name().unwrap_or_default().to_string_lossy()) == Some(day)
+        })
+        .collect();
+    examples.sort();
+
+    for path in examples {
+        let name = path.display().to_string();
+        let expected = fs::read_to_string(path.with_extension("answers"))
+            .map_or(Ok(vec![]), |text| parse_answers(&text));
+
+        let outcomes = expected.and_then(|expected| {
+            let input = Input::load(day, &Source::Path(path)).map_err(|err| err.to_string())?;
+            check(day, &input, &expected)
+        });
+        log_outcomes(&name, outcomes);
+    }
+
+    let outcomes = Input::load(day, &Source::Default)
+        .map_err(|err| err.to_string())
+        .and_then(|input| {
+            let registry = Registry::load(&submit::registry_path()).map_err(|e| e.to_string())?;
+            let expected: Vec<(u8, String)> = [1, 2]
+                .into_iter()
+                .filter_map(|part| {
+                    registry
+                        .right_answer(day, part)
+                        .map(|answer| (part, answer.to_string()))
+                })
+                .collect();
+            check(day, &input, &expected)
+        });
+    log_outcomes(&format!("day {} input", day), outcomes);
+}
+
+/// Rebuild and check `day` every time its files change
+pub fn run(day: u8) {
+    let cache = fetch::cache_dir();
+    let dirs = [Path::new("./src/inputs"), cache.as_path()];
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let mut last = snapshot(&watched_files(day, &dirs));
+    let mut changed: Vec<PathBuf> = vec![];
+
+    loop {
+        match changed.as_slice() {
+            [] => info!("--- day {} ---", day),
+            files => info!(
+                "--- day {}, changed {} ---",
+                day,
+                files
+                    .iter()
+                    .map(|f| f.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+
+        let status = Command::new(&cargo)
+            .args(["run", "--quiet", "--", "check", &day.to_string()])
+            .status();
+        if let Err(err) = status {
+            error!("Couldn't run {}: {}", cargo, err);
+        }
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let current = snapshot(&watched_files(day, &dirs));
+            changed = changes(&last, &current);
+            last = current;
+
+            if !changed.is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watched_files() {
+        let files = watched_files(4, &[Path::new("./src/inputs"), Path::new("./missing")]);
+
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("./src/day_4.rs"),
+                PathBuf::from("./src/inputs/day_4.txt"),
+                PathBuf::from("./src/inputs/day_4_ex_1.answers"),
+                PathBuf::from("./src/inputs/day_4_ex_1.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let a = PathBuf::from("a");
+        let b = PathBuf::from("b");
+        let t0 = Some(SystemTime::UNIX_EPOCH);
+        let t1 = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+
+        let before = vec![(a.clone(), t0), (b.clone(), t0)];
+        assert!(changes(&before, &before).is_empty());
+        assert_eq!(
+            changes(&before, &[(a.clone(), t0), (b.clone(), t1)]),
+            vec![b.clone()]
+        );
+        assert_eq!(changes(&before, &[(a.clone(), t0)]), vec![b.clone()]);
+        assert_eq!(changes(&[(a.clone(), t0)], &before), vec![b]);
+    }
+
+    #[test]
+    fn test_check() {
+        let input = Input::load(4, &Source::Path("./src/inputs/day_4_ex_1.txt".into())).unwrap();
+
+        let outcomes = check(4, &input, &[(1, "13".to_string()), (2, "31".to_string())]).unwrap();
+        assert_eq!(
+            outcomes.iter().map(|o| o.status()).collect::<Vec<_>>(),
+            vec!["pass", "FAIL"]
+        );
+        assert_eq!(check(4, &input, &[]).unwrap()[0].status(), "?");
+
+        let broken = Input::new("broken", "Card 1: 1 2");
+        assert!(check(4, &broken, &[]).unwrap_err().contains("Missing"));
+        assert_eq!(
+            check(9, &input, &[]).unwrap_err(),
+            "Day 9 isn't solved".to_string()
+        );
+    }
+}
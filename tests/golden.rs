@@ -0,0 +1,44 @@
+use std::{fs, path::Path};
+
+use advent::input::{example_day, parse_answers, Input, Source};
+
+#[test]
+fn test_examples() {
+    let inputs = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/inputs");
+
+    let mut examples: Vec<(u8, String)> = fs::read_dir(&inputs)
+        .expect("Couldn't read inputs directory")
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter_map(|name| example_day(&name).map(|day| (day, name)))
+        .collect();
+    examples.sort();
+
+    for (day, name) in &examples {
+        let path = inputs.join(name);
+        let input =
+            Input::load(*day, &Source::Path(path.clone())).unwrap_or_else(|err| panic!("{}", err));
+        let expected = fs::read_to_string(path.with_extension("answers"))
+            .unwrap_or_else(|_| panic!("No expected answers for {}", name));
+        let expected = parse_answers(&expected).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        assert!(!expected.is_empty(), "No expected answers for {}", name);
+
+        let answers =
+            advent::answers(*day, &input).unwrap_or_else(|| panic!("Day {} isn't solved", day));
+
+        for (part, answer) in expected {
+            let found = answers
+                .iter()
+                .find(|(p, _)| *p == part)
+                .map(|(_, a)| a.as_str());
+            assert_eq!(found, Some(answer.as_str()), "{} part {}", name, part);
+        }
+    }
+
+    for day in 1..=5 {
+        assert!(
+            examples.iter().any(|(d, _)| *d == day),
+            "No example for day {}",
+            day
+        );
+    }
+}